@@ -0,0 +1,116 @@
+//! Large block letter banners, drawn with a small built-in font
+
+/// The number of rows in every glyph
+const HEIGHT: usize = 5;
+
+/// Returns the rows of the glyph for `c`, with `#` marking filled cells. Lowercase letters use
+/// the uppercase glyphs, and characters without a glyph are drawn as `?`.
+fn glyph(c: char) -> [&'static str; HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [" ### ", "#   #", "#####", "#   #", "#   #"],
+        'B' => ["#### ", "#   #", "#### ", "#   #", "#### "],
+        'C' => [" ####", "#    ", "#    ", "#    ", " ####"],
+        'D' => ["#### ", "#   #", "#   #", "#   #", "#### "],
+        'E' => ["#####", "#    ", "#### ", "#    ", "#####"],
+        'F' => ["#####", "#    ", "#### ", "#    ", "#    "],
+        'G' => [" ####", "#    ", "#  ##", "#   #", " ####"],
+        'H' => ["#   #", "#   #", "#####", "#   #", "#   #"],
+        'I' => ["###", " # ", " # ", " # ", "###"],
+        'J' => ["  ###", "    #", "    #", "#   #", " ### "],
+        'K' => ["#   #", "#  # ", "###  ", "#  # ", "#   #"],
+        'L' => ["#    ", "#    ", "#    ", "#    ", "#####"],
+        'M' => ["#   #", "## ##", "# # #", "#   #", "#   #"],
+        'N' => ["#   #", "##  #", "# # #", "#  ##", "#   #"],
+        'O' => [" ### ", "#   #", "#   #", "#   #", " ### "],
+        'P' => ["#### ", "#   #", "#### ", "#    ", "#    "],
+        'Q' => [" ### ", "#   #", "# # #", "#  # ", " ## #"],
+        'R' => ["#### ", "#   #", "#### ", "#  # ", "#   #"],
+        'S' => [" ####", "#    ", " ### ", "    #", "#### "],
+        'T' => ["#####", "  #  ", "  #  ", "  #  ", "  #  "],
+        'U' => ["#   #", "#   #", "#   #", "#   #", " ### "],
+        'V' => ["#   #", "#   #", "#   #", " # # ", "  #  "],
+        'W' => ["#   #", "#   #", "# # #", "## ##", "#   #"],
+        'X' => ["#   #", " # # ", "  #  ", " # # ", "#   #"],
+        'Y' => ["#   #", " # # ", "  #  ", "  #  ", "  #  "],
+        'Z' => ["#####", "   # ", "  #  ", " #   ", "#####"],
+        '0' => [" ### ", "#  ##", "# # #", "##  #", " ### "],
+        '1' => [" # ", "## ", " # ", " # ", "###"],
+        '2' => [" ### ", "#   #", "  ## ", " #   ", "#####"],
+        '3' => ["#### ", "    #", " ### ", "    #", "#### "],
+        '4' => ["#   #", "#   #", "#####", "    #", "    #"],
+        '5' => ["#####", "#    ", "#### ", "    #", "#### "],
+        '6' => [" ### ", "#    ", "#### ", "#   #", " ### "],
+        '7' => ["#####", "    #", "   # ", "  #  ", "  #  "],
+        '8' => [" ### ", "#   #", " ### ", "#   #", " ### "],
+        '9' => [" ### ", "#   #", " ####", "    #", " ### "],
+        ' ' => ["   ", "   ", "   ", "   ", "   "],
+        '!' => ["#", "#", "#", " ", "#"],
+        '.' => [" ", " ", " ", " ", "#"],
+        ',' => ["  ", "  ", "  ", " #", "# "],
+        '\'' => ["#", "#", " ", " ", " "],
+        '-' => ["   ", "   ", "###", "   ", "   "],
+        ':' => [" ", "#", " ", "#", " "],
+        '/' => ["    #", "   # ", "  #  ", " #   ", "#    "],
+        _ => [" ### ", "#   #", "  ## ", "     ", "  #  "],
+    }
+}
+
+/// Renders `text` as block letters, one string per row, all of the same width. If `max_width` is
+/// given, glyphs that would extend past it are left off, and the second value returned is true.
+pub fn render(text: &str, max_width: Option<usize>) -> (Vec<String>, bool) {
+    let mut rows = vec![String::new(); HEIGHT];
+    let mut width = 0;
+    let mut truncated = false;
+    for c in text.chars() {
+        let glyph = glyph(c);
+        let glyph_width = glyph[0].len();
+        // Every glyph after the first is separated from the previous one by a column
+        let needed = if width == 0 {
+            glyph_width
+        } else {
+            glyph_width + 1
+        };
+        if max_width.is_some_and(|max| width + needed > max) {
+            truncated = true;
+            break;
+        }
+
+        for (row, line) in rows.iter_mut().zip(glyph) {
+            if width > 0 {
+                row.push(' ');
+            }
+            row.extend(line.chars().map(|cell| if cell == '#' { '█' } else { ' ' }));
+        }
+        width += needed;
+    }
+
+    (rows, truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banner_matches_golden_output() {
+        let (rows, truncated) = render("Hi", None);
+        assert!(!truncated);
+        assert_eq!(
+            rows,
+            [
+                "█   █ ███",
+                "█   █  █ ",
+                "█████  █ ",
+                "█   █  █ ",
+                "█   █ ███",
+            ]
+        );
+    }
+
+    #[test]
+    fn banner_is_cut_short_to_the_width() {
+        let (rows, truncated) = render("HI", Some(8));
+        assert!(truncated);
+        assert_eq!(rows[0], "█   █");
+    }
+}
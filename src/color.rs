@@ -0,0 +1,426 @@
+use rand::Rng;
+use termcolor::ColorSpec;
+
+/// The lightness bounds used for randomly generated colors
+pub const COLOR_LIGHTNESS_LOWER: f32 = 0.5;
+pub const COLOR_LIGHTNESS_UPPER: f32 = 0.9;
+/// The lightness bounds used for randomly generated colors on a light terminal background
+pub const COLOR_LIGHTNESS_LOWER_LIGHT_BG: f32 = 0.2;
+pub const COLOR_LIGHTNESS_UPPER_LIGHT_BG: f32 = 0.45;
+/// The saturation bounds used for randomly generated colors
+pub const COLOR_SATURATION_LOWER: f32 = 1.0;
+pub const COLOR_SATURATION_UPPER: f32 = 1.0;
+
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+    let chroma = (1. - f32::abs(2. * l - 1.)) * s;
+    let h_prime = h * 6.; // H' = H / 60deg
+    let x = chroma * (1. - f32::abs(f32::rem_euclid(h_prime, 2.) - 1.)); // X = C * (1 - |H' mod 2 - 1|)
+    let (r1, g1, b1) = if (0. ..1.).contains(&h_prime) {
+        (chroma, x, 0.)
+    } else if h_prime < 2. {
+        (x, chroma, 0.)
+    } else if h_prime < 3. {
+        (0., chroma, x)
+    } else if h_prime < 4. {
+        (0., x, chroma)
+    } else if h_prime < 5. {
+        (x, 0., chroma)
+    } else {
+        (chroma, 0., x)
+    };
+
+    let m = l - (chroma / 2.);
+    let r = (r1 + m) * 255.;
+    let g = (g1 + m) * 255.;
+    let b = (b1 + m) * 255.;
+    (r as u8, g as u8, b as u8)
+}
+
+pub fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255., g as f32 / 255., b as f32 / 255.);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.;
+    let chroma = max - min;
+    if chroma == 0. {
+        return (0., 0., l);
+    }
+
+    let s = chroma / (1. - f32::abs(2. * l - 1.));
+    let h_prime = if max == r {
+        f32::rem_euclid((g - b) / chroma, 6.)
+    } else if max == g {
+        (b - r) / chroma + 2.
+    } else {
+        (r - g) / chroma + 4.
+    };
+    (h_prime / 6., s, l)
+}
+
+/// The color a fraction `t` of the way from `from` to `to`, interpolated in HSL and going
+/// the short way around the hues
+pub fn interpolate(from: termcolor::Color, to: termcolor::Color, t: f32) -> termcolor::Color {
+    let (h1, s1, l1) = rgb_to_hsl(to_rgb(from));
+    let (h2, s2, l2) = rgb_to_hsl(to_rgb(to));
+    let hue_delta = (h2 - h1 + 0.5).rem_euclid(1.) - 0.5;
+    let (r, g, b) = hsl_to_rgb(
+        (h1 + hue_delta * t).rem_euclid(1.),
+        s1 + (s2 - s1) * t,
+        l1 + (l2 - l1) * t,
+    );
+    termcolor::Color::Rgb(r, g, b)
+}
+
+/// Returns a [termcolor::Color] with a random hue, and a lightness and saturation within the
+/// provided `(lower, upper)` bounds (minimum 0.0, maximum 1.0)
+pub fn random_color(lightness: (f32, f32), saturation: (f32, f32)) -> termcolor::Color {
+    let mut rng = rand::thread_rng();
+    let (r, g, b) = hsl_to_rgb(
+        rng.gen_range(0.0..1.0),
+        rng.gen_range(saturation.0..=saturation.1),
+        rng.gen_range(lightness.0..=lightness.1),
+    );
+    termcolor::Color::Rgb(r, g, b)
+}
+
+/// Mixes the bits of a hash (murmur3's finalizer) so lines differing by one character land far
+/// apart
+pub fn mix(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^= hash >> 33;
+    hash
+}
+
+/// Returns a [termcolor::Color] picked deterministically from `hash`, with a lightness and
+/// saturation within the `(lower, upper)` bounds, so the same hash always produces the same
+/// color
+pub fn hashed_color(hash: u64, lightness: (f32, f32), saturation: (f32, f32)) -> termcolor::Color {
+    let hash = mix(hash);
+    let hue = (hash & 0xffff_ffff) as f32 / (u32::MAX as f32 + 1.);
+    let lightness_frac = ((hash >> 32) & 0xffff) as f32 / 65536.;
+    let saturation_frac = (hash >> 48) as f32 / 65536.;
+    let (r, g, b) = hsl_to_rgb(
+        hue,
+        saturation.0 + saturation_frac * (saturation.1 - saturation.0),
+        lightness.0 + lightness_frac * (lightness.1 - lightness.0),
+    );
+    termcolor::Color::Rgb(r, g, b)
+}
+
+/// Parses bounds given as `MIN..MAX`, or a single value for a fixed level. Both must be
+/// between 0.0 and 1.0, with `MIN` no greater than `MAX`.
+pub fn parse_bounds(value: &str) -> Option<(f32, f32)> {
+    let (lower, upper) = value.split_once("..").unwrap_or((value, value));
+    let (lower, upper): (f32, f32) = (lower.parse().ok()?, upper.parse().ok()?);
+    ((0.0..=1.0).contains(&lower) && (lower..=1.0).contains(&upper)).then_some((lower, upper))
+}
+
+/// The names accepted by [parse_color], in ANSI order
+const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright-black",
+    "bright-red",
+    "bright-green",
+    "bright-yellow",
+    "bright-blue",
+    "bright-magenta",
+    "bright-cyan",
+    "bright-white",
+];
+
+/// Parses a color given as `#rrggbb` or one of the 16 ANSI color names. The 8 basic colors are
+/// returned as named colors, and the bright ones as their palette index, which [set_fg] writes
+/// as the intense named color.
+pub fn parse_color(value: &str) -> Result<termcolor::Color, String> {
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+            return Ok(termcolor::Color::Rgb(channel(0), channel(2), channel(4)));
+        }
+    }
+
+    match COLOR_NAMES.iter().position(|name| *name == value) {
+        Some(i) if i < 8 => Ok(ANSI_NAMED[i]),
+        Some(i) => Ok(termcolor::Color::Ansi256(i as u8)),
+        None => Err(format!(
+            "invalid color '{value}' (expected #rrggbb or one of {})",
+            COLOR_NAMES.join(", ")
+        )),
+    }
+}
+
+/// How many colors the terminal can display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    pub fn parse(value: &str) -> Option<ColorDepth> {
+        match value {
+            "truecolor" => Some(ColorDepth::TrueColor),
+            "256" => Some(ColorDepth::Ansi256),
+            "16" => Some(ColorDepth::Ansi16),
+            _ => None,
+        }
+    }
+}
+
+/// The default RGB values of the 16 ANSI colors in xterm
+const ANSI16_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The channel levels of the 6x6x6 color cube in the xterm 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// The index of the closest color to `rgb` in the xterm 256 color palette, choosing between
+/// the color cube and the grayscale ramp
+pub fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+
+    // The grayscale ramp runs from 8 to 238 in steps of 10
+    let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray_step = (avg.saturating_sub(8) / 10).min(23) as u8;
+    let gray_level = 8 + gray_step * 10;
+
+    if distance(rgb, (gray_level, gray_level, gray_level)) < distance(rgb, cube) {
+        232 + gray_step
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+/// The index of the closest of the 16 ANSI colors to `rgb`
+pub fn nearest_ansi16(rgb: (u8, u8, u8)) -> u8 {
+    (0..ANSI16_RGB.len())
+        .min_by_key(|&i| distance(rgb, ANSI16_RGB[i]))
+        .unwrap() as u8
+}
+
+/// The 8 basic ANSI colors in palette order, which termcolor writes by name rather than by
+/// palette index
+const ANSI_NAMED: [termcolor::Color; 8] = [
+    termcolor::Color::Black,
+    termcolor::Color::Red,
+    termcolor::Color::Green,
+    termcolor::Color::Yellow,
+    termcolor::Color::Blue,
+    termcolor::Color::Magenta,
+    termcolor::Color::Cyan,
+    termcolor::Color::White,
+];
+
+/// The named color for one of the 16 ANSI palette indices, and whether it is the bright
+/// (intense) variant
+fn ansi16_named(index: u8) -> (termcolor::Color, bool) {
+    (ANSI_NAMED[(index % 8) as usize], index >= 8)
+}
+
+/// Converts a color to the closest one a terminal with `depth` colors can show, along with
+/// whether it has to be written as intense. The 16 ANSI colors always come back as named
+/// colors, since 16 color terminals don't understand palette indices.
+pub fn downconvert(color: termcolor::Color, depth: ColorDepth) -> (termcolor::Color, bool) {
+    use termcolor::Color;
+    match (color, depth) {
+        (Color::Ansi256(i), _) if i < 16 => ansi16_named(i),
+        (Color::Rgb(..) | Color::Ansi256(_), ColorDepth::Ansi16) => {
+            ansi16_named(nearest_ansi16(to_rgb(color)))
+        }
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => {
+            (Color::Ansi256(nearest_ansi256((r, g, b))), false)
+        }
+        _ => (color, false),
+    }
+}
+
+/// Sets the foreground of `spec` to `color`, converted for a terminal with `depth` colors
+pub fn set_fg(spec: &mut ColorSpec, color: termcolor::Color, depth: ColorDepth) {
+    let (color, intense) = downconvert(color, depth);
+    spec.set_fg(Some(color)).set_intense(intense);
+}
+
+/// Sets the background of `spec` to `color`, converted for a terminal with `depth` colors. A
+/// spec has a single intense flag, which is left to the foreground, so a bright background is
+/// shown as its normal variant.
+pub fn set_bg(spec: &mut ColorSpec, color: termcolor::Color, depth: ColorDepth) {
+    spec.set_bg(Some(downconvert(color, depth).0));
+}
+
+/// The bright variant of a named color, as a palette index. Other colors are returned
+/// unchanged.
+pub fn intense(color: termcolor::Color) -> termcolor::Color {
+    match ANSI_NAMED.iter().position(|named| *named == color) {
+        Some(i) => termcolor::Color::Ansi256(i as u8 + 8),
+        None => color,
+    }
+}
+
+/// The approximate RGB value of any color, using the xterm defaults for palette colors
+pub fn to_rgb(color: termcolor::Color) -> (u8, u8, u8) {
+    use termcolor::Color;
+    let index = match color {
+        Color::Rgb(r, g, b) => return (r, g, b),
+        Color::Ansi256(i) => i,
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        _ => 7,
+    };
+    match index {
+        0..=15 => ANSI16_RGB[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// The relative luminance of an RGB color, from 0.0 (black) to 1.0 (white)
+pub fn luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.
+}
+
+/// A background that keeps `fg` readable: dark behind light colors and light behind dark ones
+pub fn contrasting_background(fg: termcolor::Color) -> termcolor::Color {
+    if luminance(to_rgb(fg)) > 0.5 {
+        termcolor::Color::Rgb(0x20, 0x20, 0x30)
+    } else {
+        termcolor::Color::Rgb(0xe0, 0xe0, 0xe8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termcolor::Color;
+
+    /// A fully saturated color at `hue` (in turns) and medium lightness
+    fn hue(hue: f32) -> (u8, u8, u8) {
+        hsl_to_rgb(hue, 1., 0.5)
+    }
+
+    #[test]
+    fn hues_map_to_the_nearest_palette_color() {
+        assert_eq!(nearest_ansi256(hue(0.)), 196);
+        assert_eq!(nearest_ansi256(hue(1. / 6.)), 226);
+        assert_eq!(nearest_ansi256(hue(1. / 3.)), 46);
+        assert_eq!(nearest_ansi256(hue(1. / 2.)), 51);
+        assert_eq!(nearest_ansi256(hue(2. / 3.)), 21);
+        assert_eq!(nearest_ansi256(hue(5. / 6.)), 201);
+        assert_eq!(nearest_ansi256((128, 128, 128)), 244);
+    }
+
+    #[test]
+    fn hues_map_to_named_colors_in_16_colors() {
+        let ansi16 =
+            |rgb: (u8, u8, u8)| downconvert(Color::Rgb(rgb.0, rgb.1, rgb.2), ColorDepth::Ansi16);
+        assert_eq!(ansi16(hue(0.)), (Color::Red, true));
+        assert_eq!(ansi16(hue(1. / 6.)), (Color::Yellow, true));
+        assert_eq!(ansi16(hue(1. / 3.)), (Color::Green, true));
+        assert_eq!(ansi16(hue(1. / 2.)), (Color::Cyan, true));
+        assert_eq!(ansi16(hue(2. / 3.)), (Color::Blue, false));
+        assert_eq!(ansi16(hue(5. / 6.)), (Color::Magenta, true));
+        assert_eq!(ansi16((205, 0, 0)), (Color::Red, false));
+        assert_eq!(ansi16((20, 20, 20)), (Color::Black, false));
+    }
+
+    #[test]
+    fn palette_colors_are_written_by_name() {
+        assert_eq!(
+            downconvert(Color::Ansi256(9), ColorDepth::TrueColor),
+            (Color::Red, true)
+        );
+        assert_eq!(
+            downconvert(Color::Ansi256(196), ColorDepth::Ansi16),
+            (Color::Red, true)
+        );
+        assert_eq!(
+            downconvert(Color::Ansi256(196), ColorDepth::Ansi256),
+            (Color::Ansi256(196), false)
+        );
+    }
+
+    #[test]
+    fn color_names_parse_to_named_colors() {
+        assert_eq!(parse_color("cyan"), Ok(Color::Cyan));
+        assert_eq!(parse_color("bright-red"), Ok(Color::Ansi256(9)));
+        assert_eq!(parse_color("#ff8000"), Ok(Color::Rgb(255, 128, 0)));
+        assert!(parse_color("orange").is_err());
+    }
+
+    #[test]
+    fn same_line_hashes_to_the_same_color() {
+        let color = |line: &str| {
+            hashed_color(
+                crate::history::line_hash(line.as_bytes()),
+                (COLOR_LIGHTNESS_LOWER, COLOR_LIGHTNESS_UPPER),
+                (COLOR_SATURATION_LOWER, COLOR_SATURATION_UPPER),
+            )
+        };
+        assert_eq!(color("Hello"), color("Hello"));
+        assert_eq!(color("  Hello "), color("Hello"));
+        assert_ne!(color("Hello"), color("Hello!"));
+        // The hash doesn't depend on the platform or the run, so neither does the color
+        assert_eq!(color("Hello"), Color::Rgb(255, 216, 84));
+    }
+
+    #[test]
+    fn interpolation_goes_the_short_way_around() {
+        let (red, blue) = (Color::Rgb(255, 0, 0), Color::Rgb(0, 0, 255));
+        assert_eq!(interpolate(red, blue, 0.), red);
+        assert_eq!(interpolate(red, blue, 1.), blue);
+        // Halfway between red and blue is magenta, not green
+        assert_eq!(interpolate(red, blue, 0.5), Color::Rgb(255, 0, 255));
+    }
+}
@@ -0,0 +1,656 @@
+//! Substitutions applied to a line's text before it is printed
+
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::layout::Align;
+use crate::logging;
+#[cfg(feature = "sysinfo")]
+use crate::sysinfo;
+use crate::{banner, host, time};
+
+/// How long a command in an `[exec:...]` token may run before it is killed
+const EXEC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Replaces `${VAR}` with the value of the environment variable `VAR`. Unset variables expand
+/// to nothing, and `$${` is written out as a literal `${`.
+pub fn env_vars(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            match after.find('}') {
+                Some(end) => {
+                    out.push_str(&std::env::var(&after[..end]).unwrap_or_default());
+                    rest = &after[end + 1..];
+                }
+                // Without a closing brace this isn't a variable, so leave it as written
+                None => {
+                    out.push_str("${");
+                    rest = after;
+                }
+            }
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Replaces control characters other than newlines and tabs with visible caret notation, in
+/// the style of `cat -v`, so text can't send its own escape sequences to the terminal
+pub fn control_chars(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c as u32 {
+            0x09 | 0x0a => out.push(c),
+            code @ 0x00..=0x1f => {
+                out.push('^');
+                out.push(char::from(code as u8 + 0x40));
+            }
+            0x7f => out.push_str("^?"),
+            code @ 0x80..=0x9f => {
+                out.push_str("M-^");
+                out.push(char::from(code as u8 - 0x40));
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Keeps the text between `[if:key=value]` and `[endif]` only when the condition holds for
+/// the current local time. Groups can be nested, and an unclosed group runs to the end of the
+/// text. Supported keys are `weekday` (`mon` to `sun`), `day`, `month`, and `hour`, each taking
+/// a comma separated list of values or inclusive ranges like `mon-fri` or `22-6`.
+pub fn conditionals(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    // Whether the condition of each enclosing group held
+    let mut groups: Vec<bool> = Vec::new();
+    let mut rest = text;
+    loop {
+        let next_if = rest.find("[if:");
+        let next_endif = rest.find("[endif]");
+        let (i, is_if) = match (next_if, next_endif) {
+            (Some(a), Some(b)) => (a.min(b), a < b),
+            (Some(a), None) => (a, true),
+            (None, Some(b)) => (b, false),
+            (None, None) => break,
+        };
+        let visible = groups.iter().all(|&held| held);
+        if visible {
+            out.push_str(&rest[..i]);
+        }
+
+        if is_if {
+            let after = &rest[i + "[if:".len()..];
+            // Without a closing bracket this isn't a condition, so leave it as written
+            let Some(end) = after.find(']') else {
+                rest = &rest[i..];
+                break;
+            };
+            groups.push(condition_holds(&after[..end]));
+            rest = &after[end + 1..];
+        } else {
+            // A stray [endif] isn't closing anything, so leave it as written
+            if groups.pop().is_none() && visible {
+                out.push_str("[endif]");
+            }
+            rest = &rest[i + "[endif]".len()..];
+        }
+    }
+    if groups.iter().all(|&held| held) {
+        out.push_str(rest);
+    }
+    out
+}
+
+fn condition_holds(condition: &str) -> bool {
+    let Some((key, values)) = condition.split_once('=') else {
+        return false;
+    };
+    let (now, parse): (_, fn(&str) -> Option<u32>) = match key.trim() {
+        "weekday" => (time::strftime("%u"), parse_weekday),
+        "day" => (time::strftime("%d"), |v| v.parse().ok()),
+        "month" => (time::strftime("%m"), |v| v.parse().ok()),
+        "hour" => (time::strftime("%H"), |v| v.parse().ok()),
+        _ => return false,
+    };
+    let Some(now) = now.and_then(|n| n.parse::<u32>().ok()) else {
+        return false;
+    };
+
+    values.split(',').any(|value| {
+        let value = value.trim();
+        match value.split_once('-') {
+            Some((start, end)) => match (parse(start.trim()), parse(end.trim())) {
+                // Ranges may wrap around, as in fri-mon or 22-6
+                (Some(start), Some(end)) if start <= end => (start..=end).contains(&now),
+                (Some(start), Some(end)) => now >= start || now <= end,
+                _ => false,
+            },
+            None => parse(value) == Some(now),
+        }
+    })
+}
+
+/// Parses a weekday name into its ISO 8601 number, where Monday is 1
+fn parse_weekday(day: &str) -> Option<u32> {
+    const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+    let day = day.to_ascii_lowercase();
+    DAYS.iter()
+        .position(|d| day.starts_with(d))
+        .map(|i| i as u32 + 1)
+        .or_else(|| day.parse().ok())
+}
+
+/// Where the picked line sits in the message file, for the `{index}` and `{total}` placeholders
+pub struct Position {
+    /// The line's index, counting from 1
+    pub index: usize,
+    /// The number of lines in the message file
+    pub total: usize,
+}
+
+/// Expands `{name}` and `{name:argument}` placeholders, such as `{date}` or `{time:%H:%M}`,
+/// and picks one alternative at random from choices like `{Hello|Hi|Greetings}`. Unrecognized
+/// placeholders are left as written, and `{{` is written out as a literal `{`. Inside braces,
+/// `\|`, `\{` and `\}` are written out as literal characters. Braces after a `$` belong to
+/// environment variables and are left alone.
+pub fn placeholders(text: &str, position: &Position) -> String {
+    expand_placeholders(text, position, false)
+}
+
+/// Expands the placeholders in `text`, where `in_braces` says whether it is an alternative of
+/// a choice, and so whether backslash escapes apply to it
+fn expand_placeholders(text: &str, position: &Position, in_braces: bool) -> String {
+    let literal = |text: &str| {
+        if in_braces {
+            unescape(text)
+        } else {
+            text.to_string()
+        }
+    };
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    loop {
+        let open = if in_braces {
+            unescaped_chars(rest)
+                .find(|&(_, c)| c == '{')
+                .map(|(i, _)| i)
+        } else {
+            rest.find('{')
+        };
+        let Some(i) = open else {
+            break;
+        };
+        out.push_str(&literal(&rest[..i]));
+        let after = &rest[i + 1..];
+        // `${` starts an environment variable, which is left for [env_vars]
+        if rest[..i].ends_with('$') {
+            out.push('{');
+            rest = after;
+            continue;
+        }
+        if let Some(after) = after.strip_prefix('{') {
+            out.push('{');
+            rest = after;
+            continue;
+        }
+
+        let Some(end) = closing_brace(after) else {
+            out.push_str(&literal(&rest[i..]));
+            return out;
+        };
+        let inner = &after[..end];
+        rest = &after[end + 1..];
+
+        let alternatives = split_alternatives(inner);
+        if alternatives.len() > 1 {
+            let choice = alternatives[rand::thread_rng().gen_range(0..alternatives.len())];
+            out.push_str(&expand_placeholders(choice, position, true));
+            continue;
+        }
+
+        let inner = unescape(inner);
+        let (name, arg) = match inner.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (inner.as_str(), None),
+        };
+        match resolve_placeholder(name, arg, position) {
+            Some(value) => out.push_str(&value),
+            None => {
+                out.push('{');
+                out.push_str(&inner);
+                out.push('}');
+            }
+        }
+    }
+    out.push_str(&literal(rest));
+    out
+}
+
+/// The characters a backslash makes literal inside braces
+const ESCAPABLE: [char; 3] = ['{', '}', '|'];
+
+/// The characters of `text` with their byte offsets, leaving out the ones escaped with a
+/// backslash along with the backslash itself
+fn unescaped_chars(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || loop {
+        let (i, c) = chars.next()?;
+        if c != '\\'
+            || chars
+                .next_if(|(_, next)| ESCAPABLE.contains(next))
+                .is_none()
+        {
+            return Some((i, c));
+        }
+    })
+}
+
+/// Replaces the escapes in `text` with the characters they stand for
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.next_if(|next| c == '\\' && ESCAPABLE.contains(next)) {
+            Some(escaped) => out.push(escaped),
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+/// Finds the `}` matching an already opened `{`, skipping over nested pairs and escapes
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in unescaped_chars(text) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the contents of a choice on the `|`s that aren't escaped or inside a nested
+/// placeholder
+fn split_alternatives(inner: &str) -> Vec<&str> {
+    let mut alternatives = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in unescaped_chars(inner) {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '|' if depth == 0 => {
+                alternatives.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    alternatives.push(&inner[start..]);
+    alternatives
+}
+
+fn resolve_placeholder(name: &str, arg: Option<&str>, position: &Position) -> Option<String> {
+    match name {
+        "index" => Some(position.index.to_string()),
+        "total" => Some(position.total.to_string()),
+        "date" => time::strftime(arg.unwrap_or("%Y-%m-%d")),
+        "time" => time::strftime(arg.unwrap_or("%H:%M")),
+        // Unknown host details expand to nothing rather than leaving the placeholder behind
+        "hostname" => Some(host::hostname().unwrap_or_default()),
+        "hostname_short" => Some(
+            host::hostname()
+                .and_then(|name| name.split('.').next().map(str::to_string))
+                .unwrap_or_default(),
+        ),
+        "user" => Some(host::username().unwrap_or_default()),
+        // Dates on the wrong side of today count as zero days
+        "days_until" => {
+            let days = time::parse_date(arg?)? - time::today()?;
+            Some(days.max(0).to_string())
+        }
+        "days_since" => {
+            let days = time::today()? - time::parse_date(arg?)?;
+            Some(days.max(0).to_string())
+        }
+        _ => sysinfo_placeholder(name),
+    }
+}
+
+/// Readings that aren't available on this platform expand to "n/a"
+#[cfg(feature = "sysinfo")]
+fn sysinfo_placeholder(name: &str) -> Option<String> {
+    let reading = match name {
+        "uptime" => sysinfo::uptime(),
+        "load1" => sysinfo::load1(),
+        "mem_used" => sysinfo::mem_used(),
+        "mem_total" => sysinfo::mem_total(),
+        "battery_pct" => sysinfo::battery_pct(),
+        _ => return None,
+    };
+    Some(reading.unwrap_or_else(|| "n/a".to_string()))
+}
+
+#[cfg(not(feature = "sysinfo"))]
+fn sysinfo_placeholder(_name: &str) -> Option<String> {
+    None
+}
+
+/// Marks where a command's output goes until [CommandOutputs::restore] puts it back. These are
+/// private use characters, which never turn up in ordinary text.
+const OUTPUT_START: char = '\u{e000}';
+const OUTPUT_END: char = '\u{e001}';
+
+/// The output of the commands run by [exec_commands], held back until the other expansions
+/// are done so that nothing in it is expanded
+pub struct CommandOutputs(Vec<String>);
+
+impl CommandOutputs {
+    /// Replaces the markers left by [exec_commands] in `text` with the output they stand for
+    pub fn restore(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(i) = rest.find(OUTPUT_START) {
+            out.push_str(&rest[..i]);
+            let after = &rest[i + OUTPUT_START.len_utf8()..];
+            let output = after.split_once(OUTPUT_END).and_then(|(index, after)| {
+                let output = self.0.get(index.parse::<usize>().ok()?)?;
+                Some((output, after))
+            });
+            match output {
+                Some((output, after)) => {
+                    out.push_str(output);
+                    rest = after;
+                }
+                None => {
+                    out.push(OUTPUT_START);
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Replaces `[exec:command]` with the trimmed standard output of running `command` in the
+/// system shell. If `allow` is false, or the command fails or times out, the token expands to
+/// nothing. The output is left out of the returned text, in place of a marker, until
+/// [CommandOutputs::restore] puts it back.
+pub fn exec_commands(text: &str, allow: bool) -> (String, CommandOutputs) {
+    let mut out = String::with_capacity(text.len());
+    let mut outputs = Vec::new();
+    let mut rest = text;
+    while let Some(i) = rest.find("[exec:") {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + "[exec:".len()..];
+        let Some(end) = after.find(']') else {
+            rest = &rest[i..];
+            break;
+        };
+
+        if allow {
+            out.push(OUTPUT_START);
+            out.push_str(&outputs.len().to_string());
+            out.push(OUTPUT_END);
+            outputs.push(run_command(&after[..end]));
+        } else {
+            logging::info!(
+                "skipped [exec:{}] (pass --allow-exec to run it)",
+                &after[..end]
+            );
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    (out, CommandOutputs(outputs))
+}
+
+fn run_command(command: &str) -> String {
+    let mut shell = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    let child = shell
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return String::new();
+    };
+
+    // Read on another thread so a chatty command can't fill the pipe and stall
+    let mut stdout = child.stdout.take().expect("child stdout should be piped");
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        let _ = sender.send(output);
+    });
+
+    let deadline = Instant::now() + EXEC_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            // A process the command left running in the background may keep the pipe open, so
+            // the output is only waited for until the deadline and dropped if it comes later
+            Ok(Some(status)) if status.success() => {
+                return receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .map(|output| output.trim().to_string())
+                    .unwrap_or_default();
+            }
+            Ok(Some(_)) => return String::new(),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            // The reader is left behind, since a process spawned by the command may still
+            // be holding the pipe open
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return String::new();
+            }
+        }
+    }
+}
+
+/// Replaces `[banner:text]` with `text` drawn in large block letters on lines of its own. When
+/// `terminal` is false the text is left plain, and when `width` is known the banner is cut
+/// short to fit it.
+pub fn banners(text: &str, terminal: bool, width: Option<usize>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find("[banner:") {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + "[banner:".len()..];
+        let Some(end) = after.find(']') else {
+            out.push_str(&rest[i..]);
+            return out;
+        };
+        rest = &after[end + 1..];
+
+        if !terminal {
+            out.push_str(&after[..end]);
+            continue;
+        }
+
+        let (rows, truncated) = banner::render(&after[..end], width);
+        if truncated {
+            logging::warning!("banner is wider than the terminal and was cut short");
+        }
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&rows.join("\n"));
+        if !rest.is_empty() {
+            out.push('\n');
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Replaces `[bell]` with the BEL character if `ring` is true, or with nothing otherwise
+pub fn bells(text: &str, ring: bool) -> String {
+    text.replace("[bell]", if ring { "\x07" } else { "" })
+}
+
+/// Replaces `[link:url|text]` with `text` as a clickable OSC 8 hyperlink to `url`, or with
+/// `text (url)` if `hyperlinks` is false. Without a `|text`, the url itself is shown.
+pub fn links(text: &str, hyperlinks: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find("[link:") {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + "[link:".len()..];
+        let Some(end) = after.find(']') else {
+            out.push_str(&rest[i..]);
+            return out;
+        };
+        rest = &after[end + 1..];
+
+        let (url, label) = match after[..end].split_once('|') {
+            Some((url, label)) => (url.trim(), label),
+            None => (after[..end].trim(), after[..end].trim()),
+        };
+        if url.is_empty() {
+            out.push_str(label);
+        } else if hyperlinks {
+            out.push_str(&format!("\x1b]8;;{url}\x1b\\{label}\x1b]8;;\x1b\\"));
+        } else if label == url {
+            out.push_str(url);
+        } else {
+            out.push_str(&format!("{label} ({url})"));
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Splits an `[align:left]`, `[align:center]`, or `[align:right]` directive off the start of
+/// `text`. Anything else is left in the text.
+pub fn alignment(text: &str) -> (Option<Align>, &str) {
+    let directive = text
+        .strip_prefix("[align:")
+        .and_then(|after| after.split_once(']'))
+        .and_then(|(value, rest)| Some((Align::parse(value.trim())?, rest)));
+    match directive {
+        Some((align, rest)) => (Some(align), rest.trim_start()),
+        None => (None, text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unclosed_condition_is_left_as_written() {
+        assert_eq!(
+            conditionals("hello [if:weekday=mon"),
+            "hello [if:weekday=mon"
+        );
+    }
+
+    #[test]
+    fn condition_without_endif_runs_to_the_end() {
+        assert_eq!(conditionals("a [if:weekday=mon-sun]b"), "a b");
+        assert_eq!(conditionals("a [if:month=13]b"), "a ");
+    }
+
+    #[test]
+    fn env_vars_are_left_to_env_expansion() {
+        let position = Position { index: 1, total: 1 };
+        assert_eq!(placeholders("${user}", &position), "${user}");
+        assert_eq!(
+            env_vars(&placeholders("v=$${user}", &position)),
+            "v=${user}"
+        );
+    }
+
+    #[test]
+    fn command_output_is_not_expanded() {
+        let position = Position { index: 1, total: 1 };
+        let (text, outputs) = exec_commands("[exec:echo '{a|b} ${HOME}'] {index}", true);
+        let text = env_vars(&placeholders(&text, &position));
+        assert_eq!(outputs.restore(&text), "{a|b} ${HOME} 1");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn background_commands_are_not_waited_for() {
+        let start = Instant::now();
+        let (text, outputs) = exec_commands("[exec:sleep 6 & echo hi]", true);
+        assert_eq!(outputs.restore(&text), "");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn escapes_are_literal_inside_braces() {
+        let position = Position { index: 1, total: 1 };
+        assert_eq!(placeholders("{ a \\|\\| b }", &position), "{ a || b }");
+        assert_eq!(placeholders("{a\\|b|a\\|b}", &position), "a|b");
+        assert_eq!(placeholders("{\\{x\\}|\\{x\\}}", &position), "{x}");
+        assert_eq!(placeholders("a\\|b", &position), "a\\|b");
+    }
+
+    #[test]
+    fn banners_are_plain_outside_a_terminal() {
+        assert_eq!(banners("[banner:Hi] there", false, None), "Hi there");
+    }
+
+    #[test]
+    fn bells_ring_only_in_a_terminal() {
+        assert_eq!(bells("ding[bell]", false), "ding");
+        assert_eq!(bells("ding[bell]", true), "ding\x07");
+    }
+
+    #[test]
+    fn control_characters_are_shown_in_caret_notation() {
+        assert_eq!(control_chars("a\x1b[31mb\tc\x7f"), "a^[[31mb\tc^?");
+        assert_eq!(control_chars("\u{9b}"), "M-^[");
+    }
+
+    #[test]
+    fn links_fall_back_to_text_and_url() {
+        assert_eq!(
+            links("[link:https://example.com|site]", false),
+            "site (https://example.com)"
+        );
+        assert_eq!(
+            links("[link:https://example.com]", true),
+            "\x1b]8;;https://example.com\x1b\\https://example.com\x1b]8;;\x1b\\"
+        );
+    }
+
+    #[test]
+    fn alignment_directive_is_split_off() {
+        assert_eq!(alignment("[align:center] hi"), (Some(Align::Center), "hi"));
+        assert_eq!(alignment("[align:middle] hi"), (None, "[align:middle] hi"));
+    }
+}
@@ -0,0 +1,61 @@
+//! Writing a message to a static file such as `/etc/motd`
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The last line of every generated file, so motd can tell its own files from others
+pub const MARKER: &str = "-- generated by motd --";
+
+/// Writes `message` followed by the [MARKER] line to `path`, replacing it atomically. An
+/// existing file without the marker is only replaced when `force` is set.
+pub fn write(path: &Path, message: &[u8], force: bool) -> io::Result<()> {
+    match fs::read_to_string(path) {
+        Ok(existing) if !force && !existing.lines().any(|line| line == MARKER) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "{} wasn't generated by motd (pass --force to replace it)",
+                    path.display()
+                ),
+            ));
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound && !force => return Err(e),
+        _ => {}
+    }
+
+    let mut contents = message.to_vec();
+    contents.extend_from_slice(MARKER.as_bytes());
+    contents.push(b'\n');
+
+    // Written next to the target and renamed over it, so readers never see a partial file. The
+    // name is random and the file must be new, so a link planted in its place isn't followed.
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (temp, mut file) = loop {
+        let temp = path.with_file_name(format!(".{name}.{:08x}.motd-tmp", rand::random::<u32>()));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => break (temp, file),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    };
+    let result = file.write_all(&contents).and_then(|_| {
+        // A replaced file keeps its permissions, and a new one is readable by everyone
+        match fs::metadata(path) {
+            Ok(metadata) => file.set_permissions(metadata.permissions()),
+            #[cfg(unix)]
+            Err(_) => {
+                use std::os::unix::fs::PermissionsExt;
+                file.set_permissions(fs::Permissions::from_mode(0o644))
+            }
+            #[cfg(not(unix))]
+            Err(_) => Ok(()),
+        }
+    });
+    drop(file);
+    result
+        .and_then(|_| fs::rename(&temp, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+}
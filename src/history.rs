@@ -0,0 +1,149 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// How previous display counts influence which line gets picked
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Weighting {
+    /// Every line is equally likely, and no history is kept
+    Uniform,
+    /// A line shown `n` times has weight `1 / (n + 1)`
+    Inverse,
+    /// A line shown `n` times has weight `1 / sqrt(n + 1)`
+    InverseSqrt,
+}
+
+impl Weighting {
+    /// Reads the weighting from the `MOTD_WEIGHTING` environment variable, defaulting to
+    /// [Weighting::Uniform] when it isn't set
+    pub fn from_env() -> Result<Weighting, String> {
+        match std::env::var("MOTD_WEIGHTING").as_deref() {
+            Err(_) | Ok("uniform") => Ok(Weighting::Uniform),
+            Ok("inverse") => Ok(Weighting::Inverse),
+            Ok("inverse-sqrt") => Ok(Weighting::InverseSqrt),
+            Ok(other) => Err(format!(
+                "unknown weighting '{other}' (expected uniform, inverse, or inverse-sqrt)"
+            )),
+        }
+    }
+
+    pub fn weight(&self, shown: u64) -> f64 {
+        let n = shown as f64 + 1.;
+        match self {
+            Weighting::Uniform => 1.,
+            Weighting::Inverse => 1. / n,
+            Weighting::InverseSqrt => 1. / n.sqrt(),
+        }
+    }
+}
+
+/// A stable 64-bit FNV-1a hash of a line's content, so display counts survive lines being
+/// moved around in the message file
+pub fn line_hash(line: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in line.trim_ascii() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Per-line display counts, persisted between runs
+pub struct History {
+    path: PathBuf,
+    counts: HashMap<u64, u64>,
+}
+
+impl History {
+    /// The location of the history file, as specified by `MOTD_HISTORY_FILE` or in the
+    /// local state directory by default
+    pub fn path() -> PathBuf {
+        std::env::var("MOTD_HISTORY_FILE")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                dirs::state_dir()
+                    .or_else(dirs::data_local_dir)
+                    .unwrap_or_default()
+                    .join("motd")
+                    .join("history")
+            })
+    }
+
+    /// Loads the history file at `path`. A missing file is an empty history, and malformed
+    /// lines are ignored.
+    pub fn load(path: &Path) -> io::Result<History> {
+        let mut counts = HashMap::new();
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                for line in contents.lines() {
+                    let Some((hash, count)) = line.split_once(' ') else {
+                        continue;
+                    };
+                    if let (Ok(hash), Ok(count)) =
+                        (u64::from_str_radix(hash, 16), count.parse::<u64>())
+                    {
+                        counts.insert(hash, count);
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(History {
+            path: path.to_path_buf(),
+            counts,
+        })
+    }
+
+    pub fn shown(&self, hash: u64) -> u64 {
+        self.counts.get(&hash).copied().unwrap_or(0)
+    }
+
+    pub fn record(&mut self, hash: u64) {
+        *self.counts.entry(hash).or_insert(0) += 1;
+    }
+
+    /// Forgets the counts of any lines no longer present in the message file
+    pub fn retain(&mut self, hashes: &HashSet<u64>) {
+        self.counts.retain(|hash, _| hashes.contains(hash));
+    }
+
+    /// Writes the counts to the history file. They are written to a new file next to it and
+    /// renamed over it, so a run interrupted while saving never leaves a truncated history.
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut contents = String::new();
+        for (hash, count) in &self.counts {
+            contents.push_str(&format!("{hash:016x} {count}\n"));
+        }
+
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = self
+            .path
+            .with_file_name(format!(".{name}.{:08x}.tmp", rand::random::<u32>()));
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        let result = file.write_all(contents.as_bytes());
+        drop(file);
+        result
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temp);
+            })
+    }
+}
+
+/// Deletes the history file, if there is one
+pub fn reset(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,102 @@
+//! Writing messages as HTML instead of terminal escape sequences
+
+use std::io::{self, Write};
+
+use termcolor::{Color, ColorSpec, WriteColor};
+
+use crate::color;
+
+/// Writes text as an HTML fragment, with colors and styles as `<span>`s with inline styles and
+/// newlines as `<br>`s
+pub struct HtmlWriter<W: Write> {
+    out: W,
+    /// Whether a `<span>` is open and needs closing before the next color
+    open: bool,
+    /// Whether the last character written was a space or a line break, so a following space
+    /// would be collapsed by the browser
+    collapsible: bool,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    pub fn new(out: W) -> Self {
+        HtmlWriter {
+            out,
+            open: false,
+            collapsible: true,
+        }
+    }
+}
+
+fn css_color(color: &Color) -> String {
+    let (r, g, b) = color::to_rgb(*color);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+impl<W: Write> Write for HtmlWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Only ASCII bytes are escaped, so multi-byte characters pass through intact
+        for &byte in buf {
+            match byte {
+                b'&' => self.out.write_all(b"&amp;")?,
+                b'<' => self.out.write_all(b"&lt;")?,
+                b'>' => self.out.write_all(b"&gt;")?,
+                b'"' => self.out.write_all(b"&quot;")?,
+                b'\'' => self.out.write_all(b"&#39;")?,
+                b'\n' => self.out.write_all(b"<br>\n")?,
+                // Runs of spaces and indentation are kept, for alignment and ASCII art
+                b' ' if self.collapsible => self.out.write_all(b"&nbsp;")?,
+                _ => self.out.write_all(&[byte])?,
+            }
+            self.collapsible = matches!(byte, b' ' | b'\n');
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> WriteColor for HtmlWriter<W> {
+    fn supports_color(&self) -> bool {
+        true
+    }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        self.reset()?;
+        let mut style = Vec::new();
+        if let Some(fg) = spec.fg() {
+            // Intense named colors are the bright half of the palette
+            let fg = if spec.intense() {
+                color::intense(*fg)
+            } else {
+                *fg
+            };
+            style.push(format!("color:{}", css_color(&fg)));
+        }
+        if let Some(bg) = spec.bg() {
+            style.push(format!("background-color:{}", css_color(bg)));
+        }
+        if spec.bold() {
+            style.push("font-weight:bold".to_string());
+        }
+        if spec.dimmed() {
+            style.push("opacity:0.5".to_string());
+        }
+        if style.is_empty() {
+            return Ok(());
+        }
+
+        write!(self.out, "<span style=\"{}\">", style.join(";"))?;
+        self.open = true;
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        if self.open {
+            self.out.write_all(b"</span>")?;
+            self.open = false;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+//! Measuring and positioning text as it will appear on the terminal
+
+use unicode_width::UnicodeWidthChar;
+
+/// Horizontal placement of text within the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    pub fn parse(value: &str) -> Option<Align> {
+        match value {
+            "left" => Some(Align::Left),
+            "center" => Some(Align::Center),
+            "right" => Some(Align::Right),
+            _ => None,
+        }
+    }
+}
+
+/// The characters a box is drawn with
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum BorderStyle {
+    #[default]
+    Rounded,
+    Square,
+    Ascii,
+}
+
+/// The pieces of a box: the top left, top right, bottom left, and bottom right corners, then
+/// the horizontal and vertical edges
+pub struct BorderChars {
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char,
+    pub horizontal: char,
+    pub vertical: char,
+}
+
+impl BorderStyle {
+    pub fn parse(value: &str) -> Option<BorderStyle> {
+        match value {
+            "rounded" => Some(BorderStyle::Rounded),
+            "square" => Some(BorderStyle::Square),
+            "ascii" => Some(BorderStyle::Ascii),
+            _ => None,
+        }
+    }
+
+    pub fn chars(&self) -> BorderChars {
+        let (corners, horizontal, vertical) = match self {
+            BorderStyle::Rounded => (['╭', '╮', '╰', '╯'], '─', '│'),
+            BorderStyle::Square => (['┌', '┐', '└', '┘'], '─', '│'),
+            BorderStyle::Ascii => (['+', '+', '+', '+'], '-', '|'),
+        };
+        BorderChars {
+            top_left: corners[0],
+            top_right: corners[1],
+            bottom_left: corners[2],
+            bottom_right: corners[3],
+            horizontal,
+            vertical,
+        }
+    }
+}
+
+/// Splits `text` into single characters and whole escape sequences, so that the sequences
+/// can be measured and written without being broken apart
+pub fn units(text: &str) -> Vec<&str> {
+    let mut units = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c == '\x1b' {
+            match chars.next() {
+                // CSI sequences end with a byte in the range @ to ~
+                Some((_, '[')) => {
+                    for (_, c) in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC sequences end with BEL or ESC \
+                Some((_, ']')) => {
+                    while let Some((_, c)) = chars.next() {
+                        if c == '\x07'
+                            || (c == '\x1b' && chars.next_if(|&(_, c)| c == '\\').is_some())
+                        {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let end = chars.peek().map_or(text.len(), |&(i, _)| i);
+        units.push(&text[start..end]);
+    }
+    units
+}
+
+/// The number of terminal columns `text` occupies. Wide characters count as two columns,
+/// combining marks and control characters as none, and escape sequences are skipped.
+pub fn display_width(text: &str) -> usize {
+    units(text)
+        .into_iter()
+        .filter(|unit| !unit.starts_with('\x1b'))
+        .flat_map(str::chars)
+        .map(|c| c.width().unwrap_or(0))
+        .sum()
+}
+
+/// Cuts `text` short to at most `width` columns, ending it with an ellipsis when anything was
+/// left out
+pub fn truncate(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for unit in units(text) {
+        let unit_width = display_width(unit);
+        if used + unit_width + 1 > width {
+            break;
+        }
+        out.push_str(unit);
+        used += unit_width;
+    }
+    out.push('…');
+    out
+}
+
+/// Replaces tabs with spaces up to the next multiple of `tabstop` columns, counting from the
+/// start of each line
+pub fn expand_tabs(text: &str, tabstop: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut column = 0;
+    for unit in units(text) {
+        match unit {
+            "\t" => {
+                let spaces = tabstop - column % tabstop;
+                out.push_str(&" ".repeat(spaces));
+                column += spaces;
+            }
+            "\n" => {
+                out.push('\n');
+                column = 0;
+            }
+            _ => {
+                out.push_str(unit);
+                column += display_width(unit);
+            }
+        }
+    }
+    out
+}
+
+/// Breaks `line` into lines no wider than `width` columns, at spaces where possible. Words
+/// wider than `width` are split between characters, unless they contain escape sequences,
+/// which are never split. Every piece keeps the original line's indentation, and spaces at the
+/// breaks are dropped.
+pub fn wrap(line: &str, width: usize) -> Vec<String> {
+    let body = line.trim_start();
+    let indent = &line[..line.len() - body.len()];
+    let width = width.saturating_sub(display_width(indent)).max(1);
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0;
+    let mut rest = body;
+    while !rest.is_empty() {
+        // Each word comes with the run of spaces before it, which is dropped at a break
+        let word = rest.trim_start_matches(' ');
+        let gap = rest.len() - word.len();
+        let word = &word[..word.find(' ').unwrap_or(word.len())];
+        rest = &rest[gap + word.len()..];
+        if word.is_empty() {
+            break;
+        }
+
+        let word_width = display_width(word);
+        if current_width > 0 && current_width + gap + word_width > width {
+            lines.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+        if current_width > 0 {
+            current.push_str(&" ".repeat(gap));
+            current_width += gap;
+        }
+
+        if word_width > width && !word.contains('\x1b') {
+            for c in word.chars() {
+                let char_width = c.width().unwrap_or(0);
+                if current_width > 0 && current_width + char_width > width {
+                    lines.push(std::mem::take(&mut current));
+                    current_width = 0;
+                }
+                current.push(c);
+                current_width += char_width;
+            }
+        } else {
+            current.push_str(word);
+            current_width += word_width;
+        }
+    }
+    lines.push(current);
+    lines.into_iter().map(|l| format!("{indent}{l}")).collect()
+}
+
+/// Pads each line of `text` on the left so that it sits at `align` within `width` columns.
+/// Lines wider than `width` are wrapped first, and each piece is aligned on its own.
+pub fn align_lines(text: &str, align: Align, width: usize) -> String {
+    if align == Align::Left {
+        return text.to_string();
+    }
+
+    let aligned: Vec<String> = text
+        .split('\n')
+        .flat_map(|line| wrap(line, width))
+        .map(|line| {
+            let free = width.saturating_sub(display_width(&line));
+            let padding = match align {
+                Align::Center => free / 2,
+                _ => free,
+            };
+            format!("{}{line}", " ".repeat(padding))
+        })
+        .collect();
+    aligned.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_and_zero_width_characters_are_measured() {
+        assert_eq!(display_width("hello"), 5);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("👍 ok"), 5);
+        assert_eq!(display_width("cafe\u{301}"), 4);
+    }
+
+    #[test]
+    fn escape_sequences_have_no_width() {
+        assert_eq!(display_width("\x1b[31mred\x1b[0m"), 3);
+        assert_eq!(
+            display_width("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"),
+            4
+        );
+        assert_eq!(
+            units("a\x1b[1mb"),
+            vec!["a", "\x1b[1m", "b"],
+            "a CSI sequence is a single unit"
+        );
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces_and_keeps_indentation() {
+        assert_eq!(wrap("hello world", 5), vec!["hello", "world"]);
+        assert_eq!(
+            wrap("  indented text here", 10),
+            vec!["  indented", "  text", "  here"]
+        );
+    }
+
+    #[test]
+    fn wrap_drops_runs_of_spaces_at_breaks() {
+        assert_eq!(wrap("a   b  c", 20), vec!["a   b  c"]);
+        assert_eq!(wrap("aa   bb  ", 4), vec!["aa", "bb"]);
+        assert_eq!(
+            wrap("    (\\_/)   (o.o)  ", 12),
+            vec!["    (\\_/)", "    (o.o)"]
+        );
+    }
+
+    #[test]
+    fn wrap_splits_wide_words_between_characters() {
+        assert_eq!(wrap("日本語テキスト", 6), vec!["日本語", "テキス", "ト"]);
+        assert_eq!(wrap("👍👍👍", 4), vec!["👍👍", "👍"]);
+    }
+
+    #[test]
+    fn wrap_never_splits_escape_sequences() {
+        let word = "\x1b[31mredredred\x1b[0m";
+        assert_eq!(wrap(word, 4), vec![word]);
+    }
+
+    #[test]
+    fn truncate_ends_with_an_ellipsis() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("hello world", 6), "hello…");
+        assert_eq!(truncate("日本語テキスト", 7), "日本語…");
+        assert_eq!(truncate("\x1b[31mhello world", 6), "\x1b[31mhello…");
+    }
+
+    #[test]
+    fn tabs_expand_to_the_next_stop() {
+        assert_eq!(expand_tabs("a\tb", 4), "a   b");
+        assert_eq!(expand_tabs("日\tb\n\tc", 4), "日  b\n    c");
+    }
+
+    #[test]
+    fn lines_are_aligned_within_the_width() {
+        assert_eq!(align_lines("abc", Align::Center, 7), "  abc");
+        assert_eq!(align_lines("abc\n日本", Align::Right, 6), "   abc\n  日本");
+        assert_eq!(align_lines("abc", Align::Left, 7), "abc");
+    }
+}
@@ -0,0 +1,103 @@
+//! Leveled diagnostics on stderr, keeping stdout for the message itself
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn parse(value: &str) -> Option<Level> {
+        match value {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn from_u8(value: u8) -> Level {
+        match value {
+            0 => Level::Error,
+            1 => Level::Warn,
+            2 => Level::Info,
+            3 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+/// The most detailed level that is written, warnings by default
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+
+/// Sets the level from the `MOTD_LOG` environment variable, raised by `verbosity` steps for
+/// each `-v` given
+pub fn init(verbosity: u8) -> Result<(), String> {
+    let level = match std::env::var("MOTD_LOG").as_deref() {
+        Err(_) | Ok("") => Level::Warn,
+        Ok(value) => Level::parse(value).ok_or_else(|| {
+            format!(
+                "unknown log level '{value}' in MOTD_LOG (expected error, warn, info, debug, or trace)"
+            )
+        })?,
+    };
+    let level = (level as u8)
+        .saturating_add(verbosity)
+        .min(Level::Trace as u8);
+    MAX_LEVEL.store(level, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn enabled(level: Level) -> bool {
+    level <= Level::from_u8(MAX_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn write(level: Level, args: fmt::Arguments) {
+    if enabled(level) {
+        eprintln!("motd: {}: {args}", level.label());
+    }
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::logging::write($crate::logging::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::logging::write($crate::logging::Level::Info, format_args!($($arg)*))
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::logging::write($crate::logging::Level::Debug, format_args!($($arg)*))
+    };
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::logging::write($crate::logging::Level::Trace, format_args!($($arg)*))
+    };
+}
+
+pub(crate) use {debug, info, trace, warning};
@@ -1,13 +1,43 @@
+mod banner;
+mod color;
+mod expand;
+mod generate;
+mod history;
+mod host;
+mod html;
+mod layout;
+mod logging;
+mod markdown;
+mod mascot;
+mod notify;
+mod printer;
+#[cfg(feature = "sysinfo")]
+mod sysinfo;
+mod term;
+mod theme;
+mod time;
+
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 
+use color::ColorDepth;
+use history::{History, Weighting};
+use html::HtmlWriter;
+use layout::BorderStyle;
+use markdown::MarkdownWriter;
+use notify::{Notification, Urgency};
+use printer::{Background, Border, ColorMode, MessagePrinter};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::Rng;
-use termcolor::{ColorSpec, StandardStream, WriteColor};
+use term::{Output, Shade};
+use theme::Theme;
 
 struct LineSeeker<R: Read + Seek> {
     reader: BufReader<R>,
     positions: Vec<usize>,
+    buf: Vec<u8>,
 }
 
 impl<R> LineSeeker<R>
@@ -18,26 +48,49 @@ where
         let mut reader = BufReader::new(read);
         let mut positions = Vec::new();
         let mut current_pos = 0;
-        let mut _buf = Vec::new();
+        // Whether the next byte read begins a new line
+        let mut line_start = true;
         loop {
-            let count = reader.read_until(b'\n', &mut _buf)?;
-            if count == 0 {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
                 break;
             }
-            positions.push(current_pos);
-            current_pos += count;
+
+            let mut offset = 0;
+            for newline in memchr::memchr_iter(b'\n', chunk) {
+                if line_start {
+                    positions.push(current_pos + offset);
+                }
+                offset = newline + 1;
+                line_start = true;
+            }
+            if line_start && offset < chunk.len() {
+                positions.push(current_pos + offset);
+                line_start = false;
+            }
+
+            let len = chunk.len();
+            current_pos += len;
+            reader.consume(len);
         }
 
-        Ok(LineSeeker { reader, positions })
+        Ok(LineSeeker {
+            reader,
+            positions,
+            buf: Vec::new(),
+        })
     }
 
     pub fn count(&self) -> usize {
         self.positions.len()
     }
 
-    pub fn get_line(&mut self, index: usize) -> io::Result<String> {
+    /// Returns the bytes of the line at `index` without its line terminator, borrowed from an
+    /// internal buffer that is reused by every call rather than allocating each time
+    pub fn get_line_bytes(&mut self, index: usize) -> io::Result<&[u8]> {
+        self.buf.clear();
         if self.positions.is_empty() {
-            return Ok(String::new());
+            return Ok(&self.buf);
         }
 
         let pos = self
@@ -46,70 +99,653 @@ where
             .expect("index for line should be in range");
         self.reader.seek(SeekFrom::Start(*pos as u64))?;
 
-        let mut buf = Vec::new();
-        self.reader.read_until(b'\n', &mut buf)?;
-        Ok(String::from_utf8(buf).expect(&format!("line {index} is not a valid utf8 string")))
+        self.reader.read_until(b'\n', &mut self.buf)?;
+        // The line terminator isn't part of the line, whether it is LF or CRLF
+        if self.buf.ends_with(b"\n") {
+            self.buf.pop();
+        }
+        if self.buf.ends_with(b"\r") {
+            self.buf.pop();
+        }
+        Ok(&self.buf)
+    }
+
+    /// Returns the line at `index` like [LineSeeker::get_line_bytes], with bytes that aren't
+    /// valid UTF-8 replaced with U+FFFD
+    pub fn get_line_ref(&mut self, index: usize) -> io::Result<Cow<'_, str>> {
+        let line = String::from_utf8_lossy(self.get_line_bytes(index)?);
+        if let Cow::Owned(_) = line {
+            logging::warning!("line {} is not valid UTF-8", index + 1);
+        }
+        Ok(line)
+    }
+}
+
+/// The lines of one or more message files, indexed as a single pool
+struct MessagePool {
+    files: Vec<LineSeeker<File>>,
+}
+
+impl MessagePool {
+    /// Opens the message file at `path`. If `path` is a directory, every `*.conf` file
+    /// inside it is read in sorted order and their lines are concatenated.
+    pub fn open(path: &Path) -> io::Result<MessagePool> {
+        if !path.is_dir() {
+            let file = File::open(path)?;
+            return Ok(MessagePool {
+                files: vec![LineSeeker::new(file)?],
+            });
+        }
+
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry_path = entry?.path();
+            if entry_path.is_file() && entry_path.extension().is_some_and(|ext| ext == "conf") {
+                paths.push(entry_path);
+            }
+        }
+        paths.sort();
+
+        // Members that can't be read are skipped rather than failing the whole pool
+        let files = paths
+            .into_iter()
+            .filter_map(|p| match File::open(&p).and_then(LineSeeker::new) {
+                Ok(file) => {
+                    logging::info!("read {} lines from {}", file.count(), p.display());
+                    Some(file)
+                }
+                Err(e) => {
+                    logging::warning!("skipping {}: {e}", p.display());
+                    None
+                }
+            })
+            .collect();
+        Ok(MessagePool { files })
+    }
+
+    pub fn count(&self) -> usize {
+        self.files.iter().map(|f| f.count()).sum()
+    }
+
+    pub fn get_line(&mut self, mut index: usize) -> io::Result<String> {
+        for file in &mut self.files {
+            if index < file.count() {
+                return file.get_line_ref(index).map(Cow::into_owned);
+            }
+            index -= file.count();
+        }
+
+        Ok(String::new())
+    }
+
+    pub fn get_line_bytes(&mut self, mut index: usize) -> io::Result<&[u8]> {
+        for file in &mut self.files {
+            if index < file.count() {
+                return file.get_line_bytes(index);
+            }
+            index -= file.count();
+        }
+
+        Ok(&[])
     }
 }
 
+/// The most columns of the message's first line shown by `--set-title`
+const TITLE_WIDTH: usize = 60;
+
 fn msg_file_path() -> PathBuf {
     std::env::var("MOTD_FILE")
-        .map(|p| PathBuf::from(p))
+        .map(PathBuf::from)
         .unwrap_or_else(|_| {
-            PathBuf::from(dirs::config_local_dir().unwrap_or_default()).join("motd.conf")
+            let path = dirs::config_dir().unwrap_or_default().join("motd.conf");
+            // Older versions read from the local config directory, which is a different one on
+            // Windows, so a file left there is still found
+            let old_path = dirs::config_local_dir()
+                .unwrap_or_default()
+                .join("motd.conf");
+            if !path.exists() && old_path.exists() {
+                old_path
+            } else {
+                path
+            }
         })
 }
 
-fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
-    let chroma = (1. - f32::abs(2. * l - 1.)) * s;
-    let h_prime = h * 6.; // H' = H / 60deg
-    let x = chroma * (1. - f32::abs(f32::rem_euclid(h_prime, 2.) - 1.)); // X = C * (1 - |H' mod 2 - 1|)
-    let (r1, g1, b1) = if 0. <= h_prime && h_prime < 1. {
-        (chroma, x, 0.)
-    } else if h_prime < 2. {
-        (x, chroma, 0.)
-    } else if h_prime < 3. {
-        (0., chroma, x)
-    } else if h_prime < 4. {
-        (0., x, chroma)
-    } else if h_prime < 5. {
-        (x, 0., chroma)
-    } else {
-        (chroma, 0., x)
-    };
+/// What the message is written as
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum Format {
+    /// Text with terminal escape sequences for colors
+    #[default]
+    Terminal,
+    /// An HTML fragment with inline styles
+    Html,
+    /// Markdown text, without colors
+    Markdown,
+}
 
-    let m = l - (chroma / 2.);
-    let r = (r1 + m) * 255.;
-    let g = (g1 + m) * 255.;
-    let b = (b1 + m) * 255.;
-    (r as u8, g as u8, b as u8)
+#[derive(Default)]
+struct Args {
+    reset_history: bool,
+    verbosity: u8,
+    no_env: bool,
+    allow_exec: bool,
+    no_bell: bool,
+    raw_controls: bool,
+    no_trim: bool,
+    fg: Option<termcolor::Color>,
+    color_mode: Option<String>,
+    rainbow_step: Option<f32>,
+    gradient: Option<(termcolor::Color, termcolor::Color)>,
+    color_depth: Option<ColorDepth>,
+    lightness: Option<(f32, f32)>,
+    saturation: Option<(f32, f32)>,
+    shade: Option<Shade>,
+    theme: Option<&'static Theme>,
+    bold: bool,
+    background: Option<Background>,
+    border: Option<Border>,
+    mascot: Option<String>,
+    notification: Option<Notification>,
+    accessible: bool,
+    set_title: bool,
+    output: Output,
+    columns: Option<usize>,
+    format: Format,
+    generate: Option<PathBuf>,
+    ansi: bool,
+    force: bool,
+    max_lines: Option<usize>,
+    tabstop: Option<usize>,
+    keep_tabs: bool,
+    plain: bool,
+    typewriter: Option<f32>,
 }
 
-/// Returns a [termcolor::Color] with a random hue, full saturation, and a lightness
-/// between the provided `lightness_lower` and `lightness_upper` bounds (minimum 0.0, maximum 1.0)
-fn random_color(lightness_lower: f32, lightness_upper: f32) -> termcolor::Color {
-    let mut rng = rand::thread_rng();
-    let (r, g, b) = hsl_to_rgb(
-        rng.gen_range(0.0..1.0),
-        1.0,
-        rng.gen_range(lightness_lower..lightness_upper),
-    );
-    termcolor::Color::Rgb(r, g, b)
+impl Args {
+    /// Turns off the cosmetic options that get in the way of screen readers, with a warning for
+    /// each one that was asked for
+    fn make_accessible(&mut self) {
+        if matches!(
+            self.color_mode.as_deref(),
+            Some("per-line" | "rainbow" | "gradient")
+        ) {
+            logging::warning!("--accessible prints the message in a single color");
+            self.color_mode = None;
+        }
+        if self.border.take().is_some() {
+            logging::warning!("--accessible turns off borders");
+        }
+        if self.mascot.take().is_some() {
+            logging::warning!("--accessible turns off speech bubbles");
+        }
+        if self.typewriter.take().is_some() {
+            logging::warning!("--accessible turns off the typewriter effect");
+        }
+        self.no_bell = true;
+    }
+
+    /// Combines `--color-mode` and `--fg`. A color given with `--fg` implies the fixed mode
+    /// unless another mode is asked for, and always wins over per-line colors.
+    fn color_mode(&self) -> Result<ColorMode, String> {
+        match (self.color_mode.as_deref(), self.fg) {
+            (None | Some("fixed" | "per-line"), Some(color)) => Ok(ColorMode::Fixed(color)),
+            (None | Some("random"), _) => Ok(ColorMode::Random),
+            (Some("hash"), _) => Ok(ColorMode::Hash),
+            (Some("per-line"), _) => Ok(ColorMode::PerLine),
+            (Some("rainbow"), _) => Ok(ColorMode::Rainbow {
+                step: self.rainbow_step.unwrap_or(0.02),
+            }),
+            (Some("gradient"), _) => Ok(ColorMode::Gradient(self.gradient)),
+            (Some("fixed"), None) => Err("--color-mode fixed needs a color from --fg".to_string()),
+            (Some(other), _) => Err(format!(
+                "unknown color mode '{other}' (expected random, fixed, hash, per-line, rainbow, or gradient)"
+            )),
+        }
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut argv = std::env::args().skip(1).peekable();
+    while let Some(arg) = argv.next() {
+        let mut value = || {
+            argv.next()
+                .ok_or_else(|| format!("missing value for '{arg}'"))
+        };
+        match arg.as_str() {
+            "--reset-history" => args.reset_history = true,
+            "-v" | "--verbose" => args.verbosity += 1,
+            "-vv" => args.verbosity += 2,
+            "-vvv" => args.verbosity += 3,
+            "--no-env" => args.no_env = true,
+            "--allow-exec" => args.allow_exec = true,
+            "--no-bell" => args.no_bell = true,
+            "--raw-controls" => args.raw_controls = true,
+            "--no-trim" => args.no_trim = true,
+            "--fg" => {
+                args.fg = match value()?.as_str() {
+                    "random" => None,
+                    color => Some(color::parse_color(color)?),
+                }
+            }
+            "--color-mode" => args.color_mode = Some(value()?),
+            "--rainbow" => args.color_mode = Some("rainbow".to_string()),
+            "--gradient" => {
+                args.color_mode = Some("gradient".to_string());
+                // The colors are optional, so a following flag isn't taken as them
+                if let Some(ends) = argv.next_if(|value| !value.starts_with('-')) {
+                    let (from, to) = ends
+                        .split_once("..")
+                        .ok_or_else(|| format!("invalid gradient '{ends}' (expected FROM..TO)"))?;
+                    args.gradient = Some((color::parse_color(from)?, color::parse_color(to)?));
+                }
+            }
+            "--rainbow-step" => {
+                let step = value()?;
+                args.rainbow_step = Some(
+                    step.parse()
+                        .map_err(|_| format!("invalid rainbow step '{step}'"))?,
+                );
+            }
+            "--color-depth" => {
+                args.color_depth = match value()?.as_str() {
+                    "auto" => None,
+                    depth => Some(ColorDepth::parse(depth).ok_or_else(|| {
+                        format!(
+                            "unknown color depth '{depth}' (expected auto, truecolor, 256, or 16)"
+                        )
+                    })?),
+                }
+            }
+            "--lightness" | "--saturation" => {
+                let bounds = value()?;
+                let bounds = color::parse_bounds(&bounds).ok_or_else(|| {
+                    format!("invalid bounds '{bounds}' (expected MIN..MAX or a single value, from 0.0 to 1.0)")
+                })?;
+                match arg.as_str() {
+                    "--lightness" => args.lightness = Some(bounds),
+                    _ => args.saturation = Some(bounds),
+                }
+            }
+            "--background" => {
+                args.shade = match value()?.as_str() {
+                    "auto" => None,
+                    shade => Some(Shade::parse(shade).ok_or_else(|| {
+                        format!("unknown background '{shade}' (expected auto, dark, or light)")
+                    })?),
+                }
+            }
+            "--theme" => args.theme = Some(theme::find(&value()?)?),
+            "--bold" => args.bold = true,
+            "--stderr" => args.output = Output::Stderr,
+            "--plain" => args.plain = true,
+            "--max-lines" => {
+                let max_lines = value()?;
+                args.max_lines = Some(
+                    max_lines
+                        .parse()
+                        .map_err(|_| format!("invalid number of lines '{max_lines}'"))?,
+                );
+            }
+            "--tabstop" => {
+                let tabstop = value()?;
+                args.tabstop = Some(
+                    tabstop
+                        .parse()
+                        .ok()
+                        .filter(|&tabstop| tabstop > 0)
+                        .ok_or_else(|| format!("invalid tab stop '{tabstop}'"))?,
+                );
+            }
+            "--keep-tabs" => args.keep_tabs = true,
+            "--generate" => args.generate = Some(PathBuf::from(value()?)),
+            "--ansi" => args.ansi = true,
+            "--force" => args.force = true,
+            "--format" => {
+                args.format = match value()?.as_str() {
+                    "terminal" => Format::Terminal,
+                    "html" => Format::Html,
+                    "markdown" => Format::Markdown,
+                    format => {
+                        return Err(format!(
+                            "unknown format '{format}' (expected terminal, html, or markdown)"
+                        ))
+                    }
+                }
+            }
+            "--columns" => {
+                let columns = value()?;
+                args.columns = Some(
+                    columns
+                        .parse()
+                        .ok()
+                        .filter(|&columns| columns > 0)
+                        .ok_or_else(|| format!("invalid number of columns '{columns}'"))?,
+                );
+            }
+            "--typewriter" => {
+                // The speed is optional, so only a following number is taken as one
+                let cps: f32 = argv
+                    .next_if(|value| value.parse::<f32>().is_ok())
+                    .map_or(40., |value| value.parse().unwrap());
+                if !cps.is_normal() || cps < 0. {
+                    return Err(format!("invalid typewriter speed '{cps}'"));
+                }
+                args.typewriter = Some(cps);
+            }
+            "--bg" => {
+                args.background = match value()?.as_str() {
+                    "none" => None,
+                    "auto-contrast" => Some(Background::AutoContrast),
+                    color => Some(Background::Fixed(color::parse_color(color)?)),
+                }
+            }
+            "--border" => {
+                args.border.get_or_insert_with(Border::default);
+            }
+            "--border-style" => {
+                let style = value()?;
+                let style = BorderStyle::parse(&style).ok_or_else(|| {
+                    format!("unknown border style '{style}' (expected rounded, square, or ascii)")
+                })?;
+                args.border.get_or_insert_with(Border::default).style = style;
+            }
+            "--border-padding" => {
+                let padding = value()?;
+                let padding = padding
+                    .parse()
+                    .map_err(|_| format!("invalid border padding '{padding}'"))?;
+                args.border.get_or_insert_with(Border::default).padding = padding;
+            }
+            "--border-color" => {
+                let color = color::parse_color(&value()?)?;
+                args.border.get_or_insert_with(Border::default).color = Some(color);
+            }
+            "--cow" => args.mascot = Some(mascot::load("cow")?),
+            "--mascot" => args.mascot = Some(mascot::load(&value()?)?),
+            "--accessible" => args.accessible = true,
+            "--set-title" => args.set_title = true,
+            "--notify" => {
+                args.notification.get_or_insert_with(Notification::default);
+            }
+            "--notify-only" => {
+                args.notification
+                    .get_or_insert_with(Notification::default)
+                    .only = true;
+            }
+            "--notify-summary" => {
+                let summary = value()?;
+                args.notification
+                    .get_or_insert_with(Notification::default)
+                    .summary = summary;
+            }
+            "--notify-urgency" => {
+                let urgency = value()?;
+                let urgency = Urgency::parse(&urgency).ok_or_else(|| {
+                    format!("unknown urgency '{urgency}' (expected low, normal, or critical)")
+                })?;
+                args.notification
+                    .get_or_insert_with(Notification::default)
+                    .urgency = urgency;
+            }
+            "--notify-timeout" => {
+                let timeout = value()?;
+                let timeout = timeout
+                    .parse()
+                    .map_err(|_| format!("invalid notification timeout '{timeout}'"))?;
+                args.notification
+                    .get_or_insert_with(Notification::default)
+                    .timeout_ms = Some(timeout);
+            }
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+    // Accessible output turns both off, so they don't conflict there
+    if args.mascot.is_some() && args.border.is_some() && !args.accessible {
+        return Err("a speech bubble can't be combined with a border".to_string());
+    }
+    Ok(args)
+}
+
+/// Picks a line index weighted by how often each line has been shown before, and records
+/// the pick in the history file. Lines are hashed as raw bytes, so they needn't be valid UTF-8.
+fn pick_weighted(lines: &mut MessagePool, weighting: Weighting) -> io::Result<usize> {
+    let mut hashes = Vec::with_capacity(lines.count());
+    for i in 0..lines.count() {
+        hashes.push(history::line_hash(lines.get_line_bytes(i)?));
+    }
+
+    let mut history = History::load(&History::path())?;
+    let weights = hashes
+        .iter()
+        .map(|hash| weighting.weight(history.shown(*hash)));
+    let index = WeightedIndex::new(weights)
+        .map(|dist| dist.sample(&mut rand::thread_rng()))
+        .unwrap_or(0);
+
+    if let Some(hash) = hashes.get(index) {
+        history.record(*hash);
+    }
+    history.retain(&hashes.into_iter().collect());
+    history.save()?;
+    Ok(index)
 }
 
 fn main() -> io::Result<()> {
-    let msg_file = File::open(msg_file_path()).unwrap_or_else(|e| {
+    let mut args = parse_args().unwrap_or_else(|e| {
+        eprintln!("motd: {e}");
+        std::process::exit(1);
+    });
+
+    term::enable_escape_sequences();
+    if let Err(e) = logging::init(args.verbosity) {
+        eprintln!("motd: {e}");
+        std::process::exit(1);
+    }
+
+    if args.accessible {
+        args.make_accessible();
+    }
+
+    if args.reset_history {
+        if let Err(e) = history::reset(&History::path()) {
+            eprintln!("motd: failed to reset history: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let weighting = Weighting::from_env().unwrap_or_else(|e| {
+        eprintln!("motd: {e}");
+        std::process::exit(1);
+    });
+
+    let path = msg_file_path();
+    let mut lines = MessagePool::open(&path).unwrap_or_else(|e| {
         eprintln!("motd: failed to open message file: {e}");
         std::process::exit(1);
     });
+    logging::info!("{} lines in {}", lines.count(), path.display());
 
-    let mut lines = LineSeeker::new(msg_file).unwrap();
-    let index = rand::thread_rng().gen_range(0..lines.count());
-    let msg = lines.get_line(index)?;
+    let index = match weighting {
+        Weighting::Uniform => rand::thread_rng().gen_range(0..lines.count().max(1)),
+        _ => pick_weighted(&mut lines, weighting).unwrap_or_else(|e| {
+            logging::warning!("failed to update history: {e}");
+            rand::thread_rng().gen_range(0..lines.count().max(1))
+        }),
+    };
+    // Conditions are expanded first so commands in hidden groups never run, and commands before
+    // anything else so a variable's value can never smuggle in an exec token. Their output is
+    // only put back after the other expansions, so it is printed exactly as written.
+    let line = lines.get_line(index)?;
+    logging::debug!("picked line {} of {}", index + 1, lines.count());
+    logging::trace!("line as written: {:?}", line);
+    let (mut msg, outputs) = expand::exec_commands(&expand::conditionals(&line), args.allow_exec);
+    let position = expand::Position {
+        index: index + 1,
+        total: lines.count(),
+    };
+    msg = expand::placeholders(&msg, &position);
+    if !args.no_env {
+        msg = expand::env_vars(&msg);
+    }
+    msg = outputs.restore(&msg);
+    // Only text from the line itself is sanitized, not the sequences motd adds below
+    if !args.raw_controls {
+        msg = expand::control_chars(&msg);
+    }
+    // Other formats are never shown by a terminal directly, even when written to one
+    let terminal =
+        args.format == Format::Terminal && args.generate.is_none() && args.output.is_terminal();
+    // Plain output has no escape sequences or terminal decorations of any kind
+    let plain = args.plain || !terminal;
+    // Piped output is only laid out to a width when one is given explicitly
+    let width = (terminal || args.columns.is_some())
+        .then(|| term::resolve_width(args.columns, args.output));
+    logging::debug!("terminal: {terminal}, width: {width:?}");
+    let trimmed = if args.no_trim {
+        msg.trim_end()
+    } else {
+        msg.trim()
+    };
+    let (align, text) = expand::alignment(trimmed);
+    // Notifications and titles show plain text, so banners, bells and links are expanded as
+    // if piped
+    let plain_text = expand::links(
+        &expand::bells(&expand::banners(text, false, None), false),
+        false,
+    );
+    if let Some(notification) = &args.notification {
+        match notification.send(&plain_text) {
+            Ok(()) if notification.only => return Ok(()),
+            Ok(()) => {}
+            Err(e) => logging::warning!("failed to send a notification: {e}"),
+        }
+    }
+    // Banners are expanded after trimming so the indentation of their first row survives. Block
+    // letters read as a string of box characters, so accessible output keeps the text.
+    msg = expand::banners(text, !plain && !args.accessible, width);
+    msg = expand::bells(&msg, !plain && !args.no_bell);
+    msg = expand::links(&msg, !plain && term::supports_hyperlinks());
+
+    let color_mode = args.color_mode().unwrap_or_else(|e| {
+        eprintln!("motd: {e}");
+        std::process::exit(1);
+    });
+    let title = plain_text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(|line| {
+            let line: String = line.chars().filter(|c| !c.is_control()).collect();
+            layout::truncate(&line, TITLE_WIDTH)
+        });
+    let lightness = args.lightness.unwrap_or_else(|| {
+        // The terminal is only asked when the colors printed depend on its background, since a
+        // terminal that doesn't answer holds up the prompt
+        let generated = !plain
+            && match color_mode {
+                ColorMode::Fixed(_) | ColorMode::Gradient(Some(_)) => false,
+                ColorMode::Rainbow { .. } | ColorMode::Gradient(None) => true,
+                // Themes pick from their own colors instead
+                ColorMode::Random | ColorMode::PerLine | ColorMode::Hash => args.theme.is_none(),
+            };
+        let shade = args.shade.unwrap_or_else(|| {
+            if generated {
+                term::background()
+            } else {
+                Shade::Dark
+            }
+        });
+        logging::debug!("background shade: {shade:?}");
+        match shade {
+            Shade::Dark => (color::COLOR_LIGHTNESS_LOWER, color::COLOR_LIGHTNESS_UPPER),
+            Shade::Light => (
+                color::COLOR_LIGHTNESS_LOWER_LIGHT_BG,
+                color::COLOR_LIGHTNESS_UPPER_LIGHT_BG,
+            ),
+        }
+    });
+    let color_depth = args.color_depth.unwrap_or_else(|| match args.format {
+        Format::Terminal => term::color_depth(),
+        Format::Html | Format::Markdown => ColorDepth::TrueColor,
+    });
+    logging::debug!("color depth: {color_depth:?}");
+    let builder = MessagePrinter::builder()
+        .color_mode(color_mode)
+        .lightness(lightness)
+        .saturation(
+            args.saturation
+                .unwrap_or((color::COLOR_SATURATION_LOWER, color::COLOR_SATURATION_UPPER)),
+        )
+        .color_depth(color_depth)
+        .palette(args.theme.map(|theme| theme.colors))
+        .bold(args.bold || args.theme.is_some_and(|theme| theme.bold))
+        .background(args.background)
+        .width(width)
+        // Box drawing characters are left out of piped output, but ASCII boxes are kept
+        .border(
+            args.border
+                .filter(|border| terminal || border.style == BorderStyle::Ascii),
+        )
+        .mascot(args.mascot)
+        .max_lines(args.max_lines)
+        .accessible(args.accessible)
+        // The title is set with an escape sequence, which only a terminal should get
+        .title(title.filter(|_| args.set_title && !plain))
+        .tabstop((!args.keep_tabs).then(|| args.tabstop.unwrap_or(8)))
+        // Colors are for looking at, not for piping elsewhere
+        .plain(plain)
+        // Typing is for watching, so piped output is printed at once
+        .typewriter(args.typewriter.filter(|_| terminal))
+        .output(args.output);
+    if terminal && args.typewriter.is_some() {
+        term::catch_interrupts();
+    }
+    let align = align.unwrap_or_default();
 
-    let mut stdout = StandardStream::stdout(termcolor::ColorChoice::Auto);
-    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(random_color(0.5, 0.9))));
-    let _ = writeln!(&mut stdout, "{}", msg.trim());
+    if let Some(path) = &args.generate {
+        let mut message = Vec::new();
+        let out = &mut message;
+        match args.format {
+            Format::Terminal if args.ansi => builder
+                .build_with(termcolor::Ansi::new(out))
+                .print_message(&msg, &line, align)?,
+            Format::Terminal => builder
+                .build_with(termcolor::NoColor::new(out))
+                .print_message(&msg, &line, align)?,
+            Format::Html => builder
+                .build_with(HtmlWriter::new(out))
+                .print_message(&msg, &line, align)?,
+            Format::Markdown => builder
+                .build_with(MarkdownWriter::new(out))
+                .print_message(&msg, &line, align)?,
+        }
+        if let Err(e) = generate::write(path, &message, args.force) {
+            eprintln!("motd: failed to generate {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    Ok(())
+    let result = match (args.format, args.output) {
+        (Format::Terminal, _) => builder.build().print_message(&msg, &line, align),
+        (Format::Html, Output::Stdout) => builder
+            .build_with(HtmlWriter::new(io::stdout()))
+            .print_message(&msg, &line, align),
+        (Format::Html, Output::Stderr) => builder
+            .build_with(HtmlWriter::new(io::stderr()))
+            .print_message(&msg, &line, align),
+        (Format::Markdown, Output::Stdout) => builder
+            .build_with(MarkdownWriter::new(io::stdout()))
+            .print_message(&msg, &line, align),
+        (Format::Markdown, Output::Stderr) => builder
+            .build_with(MarkdownWriter::new(io::stderr()))
+            .print_message(&msg, &line, align),
+    };
+    match result {
+        // A reader that stops early, like `head`, isn't an error
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
 }
@@ -0,0 +1,104 @@
+//! Writing messages as Markdown instead of terminal escape sequences
+
+use std::io::{self, Write};
+
+use termcolor::{ColorSpec, WriteColor};
+
+/// Writes text as Markdown. Colors are dropped, bold text is wrapped in `**`, and characters
+/// Markdown would interpret are escaped.
+pub struct MarkdownWriter<W: Write> {
+    out: W,
+    /// Whether a `**` is open and needs closing before the next style
+    bold: bool,
+    /// Whether the last character written was a space or a line break, so a following space
+    /// would be collapsed or start a code block
+    collapsible: bool,
+    /// Whether the text since the last space or line break is all digits, so a following `.` or
+    /// `)` would make it an ordered list marker
+    list_number: bool,
+}
+
+impl<W: Write> MarkdownWriter<W> {
+    pub fn new(out: W) -> Self {
+        MarkdownWriter {
+            out,
+            bold: false,
+            collapsible: true,
+            list_number: false,
+        }
+    }
+}
+
+impl<W: Write> Write for MarkdownWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            match byte {
+                b'\\' | b'`' | b'*' | b'_' | b'[' | b']' | b'<' | b'>' | b'#' | b'|' | b'~'
+                | b'&' => self.out.write_all(&[b'\\', byte])?,
+                // A list marker or a heading underline (`---` or `===`) is only special at the
+                // start of a line
+                b'-' | b'+' | b'=' if self.collapsible => self.out.write_all(&[b'\\', byte])?,
+                b'.' | b')' if self.list_number => self.out.write_all(&[b'\\', byte])?,
+                // Two trailing spaces make a line break rather than joining the lines
+                b'\n' => self.out.write_all(b"  \n")?,
+                b' ' if self.collapsible => self.out.write_all(b"&nbsp;")?,
+                _ => self.out.write_all(&[byte])?,
+            }
+            self.list_number = byte.is_ascii_digit() && (self.collapsible || self.list_number);
+            self.collapsible = matches!(byte, b' ' | b'\n');
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> WriteColor for MarkdownWriter<W> {
+    fn supports_color(&self) -> bool {
+        true
+    }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        if spec.bold() != self.bold {
+            self.out.write_all(b"**")?;
+            self.bold = spec.bold();
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.set_color(&ColorSpec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markdown(text: &str) -> String {
+        let mut out = Vec::new();
+        MarkdownWriter::new(&mut out)
+            .write_all(text.as_bytes())
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn entities_and_list_numbers_are_escaped() {
+        assert_eq!(markdown("&amp;"), "\\&amp;");
+        assert_eq!(markdown("1. foo"), "1\\. foo");
+        assert_eq!(markdown("10) foo\n2. bar"), "10\\) foo  \n2\\. bar");
+        assert_eq!(markdown("version 2.0"), "version 2\\.0");
+        assert_eq!(markdown("v2.0"), "v2.0");
+    }
+
+    #[test]
+    fn heading_underlines_are_escaped() {
+        assert_eq!(markdown("Title\n==="), "Title  \n\\===");
+        assert_eq!(markdown("Title\n---"), "Title  \n\\---");
+        assert_eq!(markdown("a = b"), "a \\= b");
+        assert_eq!(markdown("a==b"), "a==b");
+    }
+}
@@ -0,0 +1,49 @@
+//! Mascots drawn below the speech bubble of `--cow`
+
+/// The built-in mascots, by name. Each starts with the tail of the speech bubble.
+const MASCOTS: [(&str, &str); 3] = [
+    (
+        "cow",
+        r"        \   ^__^
+         \  (oo)\_______
+            (__)\       )\/\
+                ||----w |
+                ||     ||",
+    ),
+    (
+        "tux",
+        r"   \
+    \
+        .--.
+       |o_o |
+       |:_/ |
+      //   \ \
+     (|     | )
+    /'\_   _/`\
+    \___)=(___/",
+    ),
+    (
+        "cat",
+        r"   \
+    \   /\_/\
+       ( o.o )
+        > ^ <",
+    ),
+];
+
+/// Looks up a built-in mascot by name, or otherwise reads one from the file at `name`
+pub fn load(name: &str) -> Result<String, String> {
+    if let Some((_, art)) = MASCOTS.iter().find(|(mascot, _)| *mascot == name) {
+        return Ok(art.to_string());
+    }
+
+    std::fs::read_to_string(name)
+        .map(|art| art.trim_end().to_string())
+        .map_err(|e| {
+            let names: Vec<_> = MASCOTS.iter().map(|(mascot, _)| *mascot).collect();
+            format!(
+                "no mascot '{name}' (expected one of {} or a file: {e})",
+                names.join(", ")
+            )
+        })
+}
@@ -0,0 +1,83 @@
+//! Sending messages as desktop notifications
+
+use std::io;
+use std::process::{Command, Stdio};
+
+/// How urgent a notification is, which affects how it's shown and whether it times out
+#[derive(Clone, Copy, Default)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    pub fn parse(value: &str) -> Option<Urgency> {
+        match value {
+            "low" => Some(Urgency::Low),
+            "normal" => Some(Urgency::Normal),
+            "critical" => Some(Urgency::Critical),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+}
+
+/// A desktop notification showing the message
+pub struct Notification {
+    /// The title of the notification, with the message as its body
+    pub summary: String,
+    pub urgency: Urgency,
+    /// How long the notification stays up, or the notification server's default if unset
+    pub timeout_ms: Option<u32>,
+    /// Whether the notification replaces printing the message, rather than accompanying it
+    pub only: bool,
+}
+
+impl Default for Notification {
+    fn default() -> Self {
+        Notification {
+            summary: "Message of the day".to_string(),
+            urgency: Urgency::Normal,
+            timeout_ms: None,
+            only: false,
+        }
+    }
+}
+
+impl Notification {
+    /// Shows `body` as a notification through `notify-send`, which talks to the freedesktop
+    /// notification server. Fails if `notify-send` is missing or no server is reachable.
+    pub fn send(&self, body: &str) -> io::Result<()> {
+        let mut command = Command::new("notify-send");
+        command.args(["--urgency", self.urgency.name()]);
+        if let Some(timeout) = self.timeout_ms {
+            command.args(["--expire-time", &timeout.to_string()]);
+        }
+        let status = command
+            .arg("--")
+            .arg(&self.summary)
+            .arg(body)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => io::Error::new(e.kind(), "notify-send isn't installed"),
+                _ => e,
+            })?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "notify-send exited with {status}"
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,693 @@
+use std::io;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use crate::color::{
+    self, ColorDepth, COLOR_LIGHTNESS_LOWER, COLOR_LIGHTNESS_UPPER, COLOR_SATURATION_LOWER,
+    COLOR_SATURATION_UPPER,
+};
+use crate::history;
+use crate::layout::{self, Align, BorderStyle};
+use crate::term::{self, Output};
+
+/// How the text color of a message is chosen
+#[derive(Clone, Copy, Default)]
+pub enum ColorMode {
+    /// A new random color every time
+    #[default]
+    Random,
+    /// Always the same color
+    Fixed(Color),
+    /// A color derived from the line's content, so each line always gets the same one
+    Hash,
+    /// A new random color for every printed line of the message
+    PerLine,
+    /// Every character a different color, sweeping around the hues by `step` (a fraction of
+    /// the full circle) per character
+    Rainbow { step: f32 },
+    /// A color for each printed line, shifting evenly from the first color to the second
+    /// between the first line and the last. Random colors are used when unset.
+    Gradient(Option<(Color, Color)>),
+}
+
+/// A box drawn around the message
+#[derive(Clone, Copy)]
+pub struct Border {
+    pub style: BorderStyle,
+    /// Columns of space between the edges of the box and the text
+    pub padding: usize,
+    /// The color of the box, or the text color if unset
+    pub color: Option<Color>,
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Border {
+            style: BorderStyle::Rounded,
+            padding: 1,
+            color: None,
+        }
+    }
+}
+
+/// The background behind the message text
+#[derive(Clone, Copy)]
+pub enum Background {
+    /// Always the same color
+    Fixed(Color),
+    /// Dark behind light text colors and light behind dark ones
+    AutoContrast,
+}
+
+/// Options controlling how messages are printed. Build one with [MessagePrinter::builder] rather
+/// than a struct literal, so new options don't break construction.
+#[non_exhaustive]
+pub struct PrinterConfig {
+    /// The bounds for the lightness of the random text color (minimum 0.0, maximum 1.0)
+    pub lightness: (f32, f32),
+    /// The bounds for the saturation of the random text color (minimum 0.0, maximum 1.0)
+    pub saturation: (f32, f32),
+    pub color_mode: ColorMode,
+    /// Colors to pick from instead of generating them, in the random, per-line and hash modes
+    pub palette: Option<&'static [Color]>,
+    /// Colors are converted to the closest ones available at this depth before printing
+    pub color_depth: ColorDepth,
+    pub bold: bool,
+    /// The background behind the text, or the terminal's own if unset
+    pub background: Option<Background>,
+    /// The width of the terminal, used for alignment and wrapping. Unset when not printing to
+    /// a terminal.
+    pub width: Option<usize>,
+    pub border: Option<Border>,
+    /// The art of a mascot to draw below the message, which is put in a speech bubble
+    pub mascot: Option<String>,
+    /// At most this many lines of the message are printed after wrapping, followed by a line
+    /// saying how many more there are
+    pub max_lines: Option<usize>,
+    /// Tabs are expanded to spaces at multiples of this many columns, or passed through if unset
+    pub tabstop: Option<usize>,
+    /// Avoid decorative characters, which screen readers read out
+    pub accessible: bool,
+    /// A title for the terminal window or tab, set before printing
+    pub title: Option<String>,
+    /// Write text only, without any colors or other escape sequences
+    pub plain: bool,
+    /// Type the text out at this many characters per second instead of printing it at once
+    pub typewriter: Option<f32>,
+}
+
+impl Default for PrinterConfig {
+    fn default() -> Self {
+        PrinterConfig {
+            lightness: (COLOR_LIGHTNESS_LOWER, COLOR_LIGHTNESS_UPPER),
+            saturation: (COLOR_SATURATION_LOWER, COLOR_SATURATION_UPPER),
+            color_mode: ColorMode::Random,
+            palette: None,
+            color_depth: ColorDepth::TrueColor,
+            bold: false,
+            background: None,
+            width: None,
+            border: None,
+            mascot: None,
+            max_lines: None,
+            tabstop: Some(8),
+            accessible: false,
+            title: None,
+            plain: false,
+            typewriter: None,
+        }
+    }
+}
+
+/// Writes fully expanded messages to an output stream in color
+pub struct MessagePrinter<W: WriteColor> {
+    out: W,
+    config: PrinterConfig,
+    /// The hue of the next character in the [ColorMode::Rainbow] mode
+    hue: f32,
+    /// The colors of the first and last lines in the [ColorMode::Gradient] mode
+    gradient: (Color, Color),
+}
+
+impl MessagePrinter<StandardStream> {
+    /// A printer writing to stdout, with colors enabled unless the environment or a plain
+    /// config says otherwise
+    pub fn stdout(config: PrinterConfig) -> Self {
+        let choice = Self::color_choice(&config);
+        MessagePrinter::new(StandardStream::stdout(choice), config)
+    }
+
+    /// A printer writing to stderr, with colors enabled unless the environment or a plain
+    /// config says otherwise
+    pub fn stderr(config: PrinterConfig) -> Self {
+        let choice = Self::color_choice(&config);
+        MessagePrinter::new(StandardStream::stderr(choice), config)
+    }
+
+    fn color_choice(config: &PrinterConfig) -> ColorChoice {
+        if config.plain {
+            ColorChoice::Never
+        } else {
+            ColorChoice::Auto
+        }
+    }
+}
+
+impl MessagePrinter<StandardStream> {
+    pub fn builder() -> PrinterBuilder {
+        PrinterBuilder::default()
+    }
+}
+
+/// Builds a [MessagePrinter] writing to stdout or stderr, starting from the default
+/// [PrinterConfig]
+#[derive(Default)]
+pub struct PrinterBuilder {
+    config: PrinterConfig,
+    output: Output,
+}
+
+impl PrinterBuilder {
+    pub fn lightness(mut self, lightness: (f32, f32)) -> Self {
+        self.config.lightness = lightness;
+        self
+    }
+
+    pub fn saturation(mut self, saturation: (f32, f32)) -> Self {
+        self.config.saturation = saturation;
+        self
+    }
+
+    pub fn color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.config.color_mode = color_mode;
+        self
+    }
+
+    pub fn palette(mut self, palette: Option<&'static [Color]>) -> Self {
+        self.config.palette = palette;
+        self
+    }
+
+    pub fn color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.config.color_depth = color_depth;
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.config.bold = bold;
+        self
+    }
+
+    pub fn background(mut self, background: Option<Background>) -> Self {
+        self.config.background = background;
+        self
+    }
+
+    pub fn width(mut self, width: Option<usize>) -> Self {
+        self.config.width = width;
+        self
+    }
+
+    pub fn border(mut self, border: Option<Border>) -> Self {
+        self.config.border = border;
+        self
+    }
+
+    pub fn mascot(mut self, mascot: Option<String>) -> Self {
+        self.config.mascot = mascot;
+        self
+    }
+
+    pub fn max_lines(mut self, max_lines: Option<usize>) -> Self {
+        self.config.max_lines = max_lines;
+        self
+    }
+
+    pub fn tabstop(mut self, tabstop: Option<usize>) -> Self {
+        self.config.tabstop = tabstop;
+        self
+    }
+
+    pub fn accessible(mut self, accessible: bool) -> Self {
+        self.config.accessible = accessible;
+        self
+    }
+
+    pub fn title(mut self, title: Option<String>) -> Self {
+        self.config.title = title;
+        self
+    }
+
+    pub fn plain(mut self, plain: bool) -> Self {
+        self.config.plain = plain;
+        self
+    }
+
+    pub fn typewriter(mut self, typewriter: Option<f32>) -> Self {
+        self.config.typewriter = typewriter;
+        self
+    }
+
+    pub fn output(mut self, output: Output) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Builds a printer writing to `out` instead of stdout or stderr
+    pub fn build_with<W: WriteColor>(self, out: W) -> MessagePrinter<W> {
+        MessagePrinter::new(out, self.config)
+    }
+
+    pub fn build(self) -> MessagePrinter<StandardStream> {
+        match self.output {
+            Output::Stdout => MessagePrinter::stdout(self.config),
+            Output::Stderr => MessagePrinter::stderr(self.config),
+        }
+    }
+}
+
+impl<W: WriteColor> MessagePrinter<W> {
+    pub fn new(out: W, config: PrinterConfig) -> Self {
+        let gradient = match config.color_mode {
+            ColorMode::Gradient(Some(ends)) => ends,
+            _ => (
+                color::random_color(config.lightness, config.saturation),
+                color::random_color(config.lightness, config.saturation),
+            ),
+        };
+        MessagePrinter {
+            out,
+            config,
+            hue: rand::random(),
+            gradient,
+        }
+    }
+
+    /// Picks a text color according to the configured [ColorMode], before it is converted to
+    /// the configured depth. `line` is the line as written in the message file, before
+    /// expansion.
+    fn pick_color(&self, line: &str) -> Color {
+        let (lightness, saturation) = (self.config.lightness, self.config.saturation);
+        match (self.config.color_mode, self.config.palette) {
+            (ColorMode::Random | ColorMode::PerLine, Some(palette)) => *palette
+                .choose(&mut rand::thread_rng())
+                .unwrap_or(&Color::White),
+            (ColorMode::Hash, Some(palette)) if !palette.is_empty() => {
+                let hash = color::mix(history::line_hash(line.as_bytes()));
+                palette[(hash % palette.len() as u64) as usize]
+            }
+            (ColorMode::Random | ColorMode::PerLine | ColorMode::Rainbow { .. }, _) => {
+                color::random_color(lightness, saturation)
+            }
+            (ColorMode::Fixed(color), _) => color,
+            (ColorMode::Gradient(_), _) => self.gradient.0,
+            (ColorMode::Hash, _) => {
+                color::hashed_color(history::line_hash(line.as_bytes()), lightness, saturation)
+            }
+        }
+    }
+
+    /// Sets the configured background on `spec`, contrasting with its text color in the
+    /// [Background::AutoContrast] mode
+    fn apply_background(&self, spec: &mut ColorSpec) {
+        let background = match self.config.background {
+            None => return,
+            Some(Background::Fixed(color)) => color,
+            Some(Background::AutoContrast) => {
+                color::contrasting_background(spec.fg().copied().unwrap_or(Color::White))
+            }
+        };
+        color::set_bg(spec, background, self.config.color_depth);
+    }
+
+    /// The color spec for printed line `index` of `count` in the message. Only changes between
+    /// lines in the [ColorMode::PerLine] and [ColorMode::Gradient] modes.
+    fn line_spec(&self, spec: &ColorSpec, line: &str, index: usize, count: usize) -> ColorSpec {
+        let mut spec = spec.clone();
+        let color = match self.config.color_mode {
+            ColorMode::PerLine if index > 0 => self.pick_color(line),
+            ColorMode::Gradient(_) if count > 1 => {
+                let (from, to) = self.gradient;
+                let t = index as f32 / (count - 1) as f32;
+                color::interpolate(from, to, t)
+            }
+            _ => return spec,
+        };
+        color::set_fg(&mut spec, color, self.config.color_depth);
+        self.apply_background(&mut spec);
+        spec
+    }
+
+    /// Switches the output to the colors of `spec`. termcolor writes a bright foreground as a
+    /// 256 color sequence, so with 16 colors it is written as bold instead, which those
+    /// terminals show in the bright variant. The Windows console API takes it as it is.
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        if self.config.color_depth == ColorDepth::Ansi16
+            && spec.intense()
+            && spec.fg().is_some()
+            && !self.out.is_synchronous()
+        {
+            let mut spec = spec.clone();
+            spec.set_intense(false).set_bold(true);
+            return self.out.set_color(&spec);
+        }
+        self.out.set_color(spec)
+    }
+
+    /// Writes `text` in the color of `spec`, or sweeping through the hues a character at a time
+    /// in the [ColorMode::Rainbow] mode. With the typewriter effect, the text is written a
+    /// character at a time with a pause after each, until Ctrl-C is pressed.
+    fn write_text(&mut self, text: &str, spec: &ColorSpec) -> io::Result<()> {
+        let step = match self.config.color_mode {
+            ColorMode::Rainbow { step } => Some(step),
+            _ => None,
+        };
+        let delay = self
+            .config
+            .typewriter
+            .map(|cps| Duration::try_from_secs_f32(1. / cps).unwrap_or(Duration::MAX));
+        if step.is_none() && delay.is_none() {
+            self.set_color(spec)?;
+            return write!(self.out, "{text}");
+        }
+
+        let lightness = (self.config.lightness.0 + self.config.lightness.1) / 2.;
+        let saturation = (self.config.saturation.0 + self.config.saturation.1) / 2.;
+        let mut spec = spec.clone();
+        self.set_color(&spec)?;
+        for unit in layout::units(text) {
+            // Escape sequences are passed through whole, without using up a hue or a pause
+            if unit.starts_with('\x1b') {
+                write!(self.out, "{unit}")?;
+                continue;
+            }
+
+            if let Some(step) = step {
+                let (r, g, b) = color::hsl_to_rgb(self.hue, saturation, lightness);
+                color::set_fg(&mut spec, Color::Rgb(r, g, b), self.config.color_depth);
+                self.set_color(&spec)?;
+                self.hue = (self.hue + step).rem_euclid(1.0);
+            }
+            write!(self.out, "{unit}")?;
+            if let Some(delay) = delay.filter(|_| !unit.trim().is_empty() && !term::interrupted()) {
+                self.out.flush()?;
+                std::thread::sleep(delay);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints `msg` followed by a newline, in a color chosen according to the configured
+    /// [ColorMode] and with each line placed according to `align`. `line` is the line as
+    /// written in the message file, before expansion.
+    pub fn print_message(&mut self, msg: &str, line: &str, align: Align) -> io::Result<()> {
+        if let Some(title) = &self.config.title {
+            write!(self.out, "\x1b]2;{title}\x07")?;
+        }
+        // Tabs are expanded first so they are measured like the spaces they become
+        let msg = &match self.config.tabstop {
+            Some(tabstop) => layout::expand_tabs(msg, tabstop),
+            None => msg.to_string(),
+        };
+        let mut spec = ColorSpec::new();
+        color::set_fg(&mut spec, self.pick_color(line), self.config.color_depth);
+        spec.set_bold(self.config.bold);
+        self.apply_background(&mut spec);
+
+        if let Some(mascot) = self.config.mascot.clone() {
+            self.print_bubble(msg, line, align, &mascot, &spec)?;
+        } else if let Some(border) = self.config.border {
+            self.print_boxed(msg, line, align, border, &spec)?;
+        } else {
+            let mut lines: Vec<String> = msg.split('\n').map(str::to_string).collect();
+            if self.config.max_lines.is_some() {
+                // Lines are usually left to the terminal to wrap, but here they need counting
+                if let Some(width) = self.config.width {
+                    lines = lines.iter().flat_map(|l| layout::wrap(l, width)).collect();
+                }
+                self.truncate(&mut lines);
+            }
+            let msg = match self.config.width {
+                Some(width) => layout::align_lines(&lines.join("\n"), align, width),
+                None => lines.join("\n"),
+            };
+            let count = msg.split('\n').count();
+            for (i, text) in msg.split('\n').enumerate() {
+                let spec = self.line_spec(&spec, line, i, count);
+                // Padding from alignment is left outside the background
+                let body = text.trim_start_matches(' ');
+                write!(self.out, "{}", &text[..text.len() - body.len()])?;
+                self.write_text(body, &spec)?;
+                // Reset before the newline so the background doesn't fill the rest of the row
+                self.out.reset()?;
+                writeln!(self.out)?;
+            }
+        }
+        self.out.reset()?;
+        self.out.flush()
+    }
+
+    /// Cuts `lines` short to the configured maximum, replacing the rest with a line saying how
+    /// many were left out
+    fn truncate(&self, lines: &mut Vec<String>) {
+        let Some(max_lines) = self.config.max_lines else {
+            return;
+        };
+        if lines.len() > max_lines {
+            let hidden = lines.len() - max_lines;
+            lines.truncate(max_lines);
+            let plural = if hidden == 1 { "" } else { "s" };
+            let ellipsis = if self.config.accessible { "..." } else { "…" };
+            lines.push(format!("{ellipsis} ({hidden} more line{plural})"));
+        }
+    }
+
+    /// Wraps the lines of `msg` to fit the terminal alongside `margin` columns of decoration
+    /// and truncates them, returning them with the width of the widest
+    fn wrap_inside(&self, msg: &str, margin: usize) -> (Vec<String>, usize) {
+        let max_inner = self
+            .config
+            .width
+            .map(|width| width.saturating_sub(margin).max(1));
+        let mut lines: Vec<String> = msg
+            .split('\n')
+            .flat_map(|line| match max_inner {
+                Some(max) => layout::wrap(line, max),
+                None => vec![line.to_string()],
+            })
+            .collect();
+        self.truncate(&mut lines);
+        let inner = lines
+            .iter()
+            .map(|line| layout::display_width(line))
+            .max()
+            .unwrap_or(0);
+        (lines, inner)
+    }
+
+    /// Prints `msg` inside a box sized to its widest line, wrapping lines that would make the
+    /// box wider than the terminal
+    fn print_boxed(
+        &mut self,
+        msg: &str,
+        line: &str,
+        align: Align,
+        border: Border,
+        text_spec: &ColorSpec,
+    ) -> io::Result<()> {
+        let (lines, inner) = self.wrap_inside(msg, 2 + 2 * border.padding);
+
+        let chars = border.style.chars();
+        let mut border_spec = ColorSpec::new();
+        match border.color {
+            Some(color) => color::set_fg(&mut border_spec, color, self.config.color_depth),
+            None => {
+                border_spec
+                    .set_fg(text_spec.fg().copied())
+                    .set_intense(text_spec.intense());
+            }
+        }
+        let edge = chars
+            .horizontal
+            .to_string()
+            .repeat(inner + 2 * border.padding);
+        let padding = " ".repeat(border.padding);
+
+        self.set_color(&border_spec)?;
+        writeln!(self.out, "{}{edge}{}", chars.top_left, chars.top_right)?;
+        for (i, text) in lines.iter().enumerate() {
+            let free = inner - layout::display_width(text);
+            let left = match align {
+                Align::Left => 0,
+                Align::Center => free / 2,
+                Align::Right => free,
+            };
+            let spec = self.line_spec(text_spec, line, i, lines.len());
+            self.set_color(&border_spec)?;
+            write!(self.out, "{}{padding}{}", chars.vertical, " ".repeat(left))?;
+            self.write_text(text, &spec)?;
+            self.set_color(&border_spec)?;
+            writeln!(
+                self.out,
+                "{}{padding}{}",
+                " ".repeat(free - left),
+                chars.vertical
+            )?;
+        }
+        writeln!(
+            self.out,
+            "{}{edge}{}",
+            chars.bottom_left, chars.bottom_right
+        )
+    }
+
+    /// Prints `msg` in a speech bubble said by `mascot`, with the bubble and mascot dimmed
+    fn print_bubble(
+        &mut self,
+        msg: &str,
+        line: &str,
+        align: Align,
+        mascot: &str,
+        text_spec: &ColorSpec,
+    ) -> io::Result<()> {
+        let (lines, inner) = self.wrap_inside(msg, 4);
+        let mut bubble_spec = ColorSpec::new();
+        bubble_spec.set_dimmed(true);
+
+        self.set_color(&bubble_spec)?;
+        writeln!(self.out, " {}", "_".repeat(inner + 2))?;
+        for (i, text) in lines.iter().enumerate() {
+            let (left_edge, right_edge) = match (i, lines.len()) {
+                (_, 1) => ('<', '>'),
+                (0, _) => ('/', '\\'),
+                (i, len) if i == len - 1 => ('\\', '/'),
+                _ => ('|', '|'),
+            };
+            let free = inner - layout::display_width(text);
+            let left = match align {
+                Align::Left => 0,
+                Align::Center => free / 2,
+                Align::Right => free,
+            };
+            let spec = self.line_spec(text_spec, line, i, lines.len());
+            self.set_color(&bubble_spec)?;
+            write!(self.out, "{left_edge} {}", " ".repeat(left))?;
+            self.write_text(text, &spec)?;
+            self.set_color(&bubble_spec)?;
+            writeln!(self.out, "{} {right_edge}", " ".repeat(free - left))?;
+        }
+        writeln!(self.out, " {}", "-".repeat(inner + 2))?;
+        writeln!(self.out, "{mascot}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use termcolor::Buffer;
+
+    use super::*;
+    use crate::html::HtmlWriter;
+
+    /// Prints `msg` with a printer from `builder` into `out`, returning what was written
+    fn render(builder: PrinterBuilder, out: Buffer, msg: &str) -> String {
+        let mut printer = builder.build_with(out);
+        printer.print_message(msg, msg, Align::Left).unwrap();
+        String::from_utf8(printer.out.into_inner()).unwrap()
+    }
+
+    fn fixed(color: Color) -> PrinterBuilder {
+        MessagePrinter::builder().color_mode(ColorMode::Fixed(color))
+    }
+
+    #[test]
+    fn fixed_color_is_written_as_escape_sequences() {
+        let out = render(fixed(Color::Rgb(1, 2, 3)), Buffer::ansi(), "hi");
+        assert_eq!(out, "\x1b[0m\x1b[38;2;1;2;3mhi\x1b[0m\n\x1b[0m");
+    }
+
+    #[test]
+    fn colors_are_named_in_16_colors() {
+        let builder = fixed(Color::Rgb(200, 0, 0)).color_depth(ColorDepth::Ansi16);
+        let out = render(builder.bold(true), Buffer::ansi(), "hi");
+        assert_eq!(out, "\x1b[0m\x1b[1m\x1b[31mhi\x1b[0m\n\x1b[0m");
+    }
+
+    #[test]
+    fn bright_colors_are_bold_in_16_colors() {
+        for color in [Color::Rgb(255, 0, 0), Color::Ansi256(9)] {
+            let builder = fixed(color).color_depth(ColorDepth::Ansi16);
+            let out = render(builder, Buffer::ansi(), "hi");
+            assert_eq!(out, "\x1b[0m\x1b[1m\x1b[31mhi\x1b[0m\n\x1b[0m");
+        }
+        // Other depths can show the bright color without making the text bold
+        let builder = fixed(Color::Ansi256(9)).color_depth(ColorDepth::Ansi256);
+        let out = render(builder, Buffer::ansi(), "hi");
+        assert_eq!(out, "\x1b[0m\x1b[38;5;9mhi\x1b[0m\n\x1b[0m");
+    }
+
+    #[test]
+    fn plain_output_is_only_text() {
+        let out = render(fixed(Color::Red), Buffer::no_color(), "hello\nworld");
+        assert_eq!(out, "hello\nworld\n");
+    }
+
+    #[test]
+    fn lines_are_aligned_to_the_width() {
+        let mut printer = fixed(Color::Red)
+            .width(Some(9))
+            .build_with(Buffer::no_color());
+        printer.print_message("abc", "abc", Align::Center).unwrap();
+        assert_eq!(
+            String::from_utf8(printer.out.into_inner()).unwrap(),
+            "   abc\n"
+        );
+    }
+
+    #[test]
+    fn long_messages_are_cut_short() {
+        let builder = fixed(Color::Red).width(Some(5)).max_lines(Some(2));
+        let out = render(builder, Buffer::no_color(), "one two three four");
+        assert_eq!(out, "one\ntwo\n… (2 more lines)\n");
+    }
+
+    #[test]
+    fn border_matches_snapshot() {
+        let border = Border {
+            style: BorderStyle::Ascii,
+            padding: 1,
+            color: None,
+        };
+        let out = render(
+            fixed(Color::Red).border(Some(border)),
+            Buffer::no_color(),
+            "hello\n日本",
+        );
+        assert_eq!(out, "+-------+\n| hello |\n| 日本  |\n+-------+\n");
+    }
+
+    #[test]
+    fn bubble_matches_snapshot() {
+        let builder = fixed(Color::Red).mascot(Some("  (o.o)".to_string()));
+        let out = render(builder, Buffer::no_color(), "hello\nthere");
+        assert_eq!(out, " _______\n/ hello \\\n\\ there /\n -------\n  (o.o)\n");
+    }
+
+    #[test]
+    fn html_matches_snapshot() {
+        let mut html = Vec::new();
+        fixed(Color::Rgb(0xff, 0x80, 0))
+            .bold(true)
+            .build_with(HtmlWriter::new(&mut html))
+            .print_message("<a & b>\n  x", "", Align::Left)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(html).unwrap(),
+            concat!(
+                "<span style=\"color:#ff8000;font-weight:bold\">&lt;a &amp; b&gt;</span><br>\n",
+                "&nbsp;&nbsp;<span style=\"color:#ff8000;font-weight:bold\">x</span><br>\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Readings about the running system, for the `sysinfo` placeholders. Anything that can't be
+//! read on the current platform comes back as `None`.
+
+use std::fs;
+
+/// Time since boot, e.g. "3 days 4 h"
+pub fn uptime() -> Option<String> {
+    let contents = fs::read_to_string("/proc/uptime").ok()?;
+    let secs = contents.split_whitespace().next()?.parse::<f64>().ok()? as u64;
+    Some(format_duration(secs))
+}
+
+/// The one minute load average
+pub fn load1() -> Option<String> {
+    let contents = fs::read_to_string("/proc/loadavg").ok()?;
+    contents.split_whitespace().next().map(str::to_string)
+}
+
+/// Memory in use, e.g. "7.2 GiB"
+pub fn mem_used() -> Option<String> {
+    let total = meminfo_kib("MemTotal")?;
+    let available = meminfo_kib("MemAvailable")?;
+    Some(format_kib(total.saturating_sub(available)))
+}
+
+/// Total installed memory, e.g. "15.5 GiB"
+pub fn mem_total() -> Option<String> {
+    meminfo_kib("MemTotal").map(format_kib)
+}
+
+/// The charge of the first battery found, e.g. "87%"
+pub fn battery_pct() -> Option<String> {
+    let mut supplies: Vec<_> = fs::read_dir("/sys/class/power_supply")
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    supplies.sort();
+
+    supplies.into_iter().find_map(|supply| {
+        let kind = fs::read_to_string(supply.join("type")).ok()?;
+        if kind.trim() != "Battery" {
+            return None;
+        }
+        let capacity = fs::read_to_string(supply.join("capacity")).ok()?;
+        Some(format!("{}%", capacity.trim()))
+    })
+}
+
+fn meminfo_kib(field: &str) -> Option<u64> {
+    let contents = fs::read_to_string("/proc/meminfo").ok()?;
+    contents.lines().find_map(|line| {
+        let value = line.strip_prefix(field)?.strip_prefix(':')?;
+        value.split_whitespace().next()?.parse().ok()
+    })
+}
+
+fn format_duration(secs: u64) -> String {
+    let days = secs / 86400;
+    let hours = secs % 86400 / 3600;
+    let mins = secs % 3600 / 60;
+    match days {
+        0 if hours == 0 => format!("{mins} min"),
+        0 => format!("{hours} h {mins} min"),
+        1 => format!("1 day {hours} h"),
+        _ => format!("{days} days {hours} h"),
+    }
+}
+
+fn format_kib(kib: u64) -> String {
+    let mib = kib as f64 / 1024.;
+    // Compared as rounded, so just under 1 GiB isn't shown as "1024 MiB"
+    if mib.round() < 1024. {
+        format!("{mib:.0} MiB")
+    } else {
+        format!("{:.1} GiB", mib / 1024.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_rounded_down_to_the_largest_units() {
+        assert_eq!(format_duration(0), "0 min");
+        assert_eq!(format_duration(59), "0 min");
+        assert_eq!(format_duration(3599), "59 min");
+        assert_eq!(format_duration(3600), "1 h 0 min");
+        assert_eq!(format_duration(86399), "23 h 59 min");
+        assert_eq!(format_duration(86400), "1 day 0 h");
+        assert_eq!(format_duration(2 * 86400 + 3 * 3600 + 59), "2 days 3 h");
+    }
+
+    #[test]
+    fn sizes_switch_to_gib_at_1024_mib() {
+        assert_eq!(format_kib(0), "0 MiB");
+        assert_eq!(format_kib(1023 * 1024), "1023 MiB");
+        assert_eq!(format_kib(1024 * 1024 - 1), "1.0 GiB");
+        assert_eq!(format_kib(1024 * 1024), "1.0 GiB");
+        assert_eq!(format_kib(16_252_928), "15.5 GiB");
+    }
+}
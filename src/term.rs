@@ -0,0 +1,357 @@
+//! Details of the terminal motd is printing to
+
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::time::{Duration, Instant};
+
+use crate::color::{self, ColorDepth};
+use crate::logging;
+
+/// The standard stream messages are printed to
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Output {
+    #[default]
+    Stdout,
+    Stderr,
+}
+
+impl Output {
+    pub fn is_terminal(self) -> bool {
+        match self {
+            Output::Stdout => io::stdout().is_terminal(),
+            Output::Stderr => io::stderr().is_terminal(),
+        }
+    }
+}
+
+/// The width to lay messages out in: `columns` if given, then the width of the terminal
+/// attached to `output`, then the `COLUMNS` environment variable, and otherwise 80
+pub fn resolve_width(columns: Option<usize>, output: Output) -> usize {
+    pick_width(
+        columns,
+        || output.is_terminal().then(|| width(output)).flatten(),
+        std::env::var("COLUMNS").ok().as_deref(),
+    )
+}
+
+/// The first width available out of `columns`, then `terminal_width`, then `columns_env` (the
+/// value of `COLUMNS`), and otherwise 80. The terminal is only asked when it is needed.
+fn pick_width(
+    columns: Option<usize>,
+    terminal_width: impl FnOnce() -> Option<usize>,
+    columns_env: Option<&str>,
+) -> usize {
+    columns
+        .or_else(terminal_width)
+        .or_else(|| {
+            columns_env
+                .and_then(|columns| columns.parse().ok())
+                .filter(|&columns| columns > 0)
+        })
+        .unwrap_or(80)
+}
+
+/// The width of the terminal attached to `output`, in columns
+#[cfg(unix)]
+fn width(output: Output) -> Option<usize> {
+    let fd = match output {
+        Output::Stdout => libc::STDOUT_FILENO,
+        Output::Stderr => libc::STDERR_FILENO,
+    };
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(windows)]
+fn width(output: Output) -> Option<usize> {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleScreenBufferInfo, CONSOLE_SCREEN_BUFFER_INFO,
+    };
+
+    let mut info: CONSOLE_SCREEN_BUFFER_INFO = unsafe { std::mem::zeroed() };
+    let result = unsafe { GetConsoleScreenBufferInfo(console_handle(output), &mut info) };
+    let columns = info.srWindow.Right - info.srWindow.Left + 1;
+    (result != 0 && columns > 0).then_some(columns as usize)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn width(_output: Output) -> Option<usize> {
+    None
+}
+
+#[cfg(windows)]
+fn console_handle(output: Output) -> windows_sys::Win32::Foundation::HANDLE {
+    use windows_sys::Win32::System::Console::{GetStdHandle, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
+
+    let handle = match output {
+        Output::Stdout => STD_OUTPUT_HANDLE,
+        Output::Stderr => STD_ERROR_HANDLE,
+    };
+    unsafe { GetStdHandle(handle) }
+}
+
+/// Turns on escape sequence processing in the Windows console, which older consoles like
+/// conhost leave off. termcolor handles colors without it, but not the other sequences motd
+/// writes, like hyperlinks and titles.
+#[cfg(windows)]
+pub fn enable_escape_sequences() {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+    };
+
+    for output in [Output::Stdout, Output::Stderr] {
+        let handle = console_handle(output);
+        let mut mode = 0;
+        // Fails when the stream isn't a console, which needs nothing enabled
+        if unsafe { GetConsoleMode(handle, &mut mode) } != 0 {
+            unsafe { SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) };
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn enable_escape_sequences() {}
+
+/// Whether the terminal is likely to understand OSC 8 hyperlinks. Most terminals that don't
+/// support them ignore the sequence, but the Linux console and dumb terminals print junk.
+pub fn supports_hyperlinks() -> bool {
+    !matches!(std::env::var("TERM").as_deref(), Ok("dumb" | "linux"))
+}
+
+/// Guesses how many colors the terminal supports from `COLORTERM` and `TERM`. Windows Terminal
+/// and the Windows console, which don't set `TERM`, take true color.
+pub fn color_depth() -> ColorDepth {
+    if matches!(
+        std::env::var("COLORTERM").as_deref(),
+        Ok("truecolor" | "24bit")
+    ) || std::env::var_os("WT_SESSION").is_some()
+    {
+        return ColorDepth::TrueColor;
+    }
+    match std::env::var("TERM") {
+        Ok(term) if term.contains("truecolor") || term.contains("direct") => ColorDepth::TrueColor,
+        Ok(term) if term.contains("256color") => ColorDepth::Ansi256,
+        Err(_) if cfg!(windows) => ColorDepth::TrueColor,
+        _ => ColorDepth::Ansi16,
+    }
+}
+
+/// Whether the terminal's background is dark or light
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shade {
+    Dark,
+    Light,
+}
+
+impl Shade {
+    pub fn parse(value: &str) -> Option<Shade> {
+        match value {
+            "dark" => Some(Shade::Dark),
+            "light" => Some(Shade::Light),
+            _ => None,
+        }
+    }
+}
+
+/// Detects the shade of the terminal's background by asking the terminal, then from
+/// `COLORFGBG`, and otherwise assumes it is dark
+pub fn background() -> Shade {
+    if let Some(rgb) = query_background() {
+        logging::debug!("the terminal reported a background color of {rgb:?}");
+        return if color::luminance(rgb) > 0.5 {
+            Shade::Light
+        } else {
+            Shade::Dark
+        };
+    }
+
+    logging::debug!("the terminal didn't report its background color");
+    // COLORFGBG is set by rxvt and Konsole to "fg;bg" (or "fg;default;bg") as palette indices
+    let colorfgbg = std::env::var("COLORFGBG").unwrap_or_default();
+    match colorfgbg
+        .rsplit(';')
+        .next()
+        .and_then(|bg| bg.parse::<u8>().ok())
+    {
+        Some(7 | 9..=15) => Shade::Light,
+        _ => Shade::Dark,
+    }
+}
+
+/// How long to wait for the terminal to answer a query
+#[cfg(unix)]
+const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Asks the terminal for its background color with an OSC 11 query. Returns `None` if the
+/// terminal doesn't answer in time.
+///
+/// The query is followed by a DA1 query, which nearly every terminal answers. Terminals answer
+/// in order, so once that reply arrives there is no color reply coming, and terminals that
+/// don't know OSC 11 don't hold up the prompt for the whole timeout.
+#[cfg(unix)]
+fn query_background() -> Option<(u8, u8, u8)> {
+    use std::io::{Read, Write};
+    use std::os::fd::AsRawFd;
+
+    let mut tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    let fd = tty.as_raw_fd();
+    let mut original: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+        return None;
+    }
+    // Read the reply as it arrives, without echoing it
+    let mut raw = original;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    raw.c_cc[libc::VMIN] = 0;
+    raw.c_cc[libc::VTIME] = 0;
+    // Ctrl-C only cuts the wait short, so the terminal is restored before motd exits
+    catch_interrupts();
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+        restore_interrupts();
+        return None;
+    }
+
+    let mut reply = Vec::new();
+    if tty
+        .write_all(b"\x1b]11;?\x1b\\\x1b[c")
+        .and_then(|_| tty.flush())
+        .is_ok()
+    {
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        while !ends_with_da1_reply(&reply) && reply.len() < 128 && !interrupted() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let mut poll = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if remaining.is_zero()
+                || unsafe { libc::poll(&mut poll, 1, remaining.as_millis() as libc::c_int) } <= 0
+            {
+                break;
+            }
+            let mut byte = [0];
+            match tty.read(&mut byte) {
+                Ok(1) => reply.push(byte[0]),
+                _ => break,
+            }
+        }
+    }
+    // Flushing discards any part of a late reply so it doesn't end up in the shell. Keys typed
+    // while waiting go with it, as they can't be told apart from the reply.
+    unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &original) };
+    restore_interrupts();
+
+    parse_color_reply(&String::from_utf8_lossy(&reply))
+}
+
+/// Whether `reply` ends with a complete answer to a DA1 query, `ESC [ ? ... c`
+#[cfg(unix)]
+fn ends_with_da1_reply(reply: &[u8]) -> bool {
+    reply.ends_with(b"c") && reply.windows(3).any(|window| window == b"\x1b[?")
+}
+
+#[cfg(not(unix))]
+fn query_background() -> Option<(u8, u8, u8)> {
+    None
+}
+
+/// Parses the `rgb:rrrr/gggg/bbbb` color in a reply to an OSC color query. Each channel has
+/// one to four hex digits.
+#[cfg(unix)]
+fn parse_color_reply(reply: &str) -> Option<(u8, u8, u8)> {
+    let rgb = &reply[reply.find("rgb:")? + 4..];
+    // The color ends at the BEL or ST (ESC \) closing the reply
+    let rgb = rgb.split(['\x07', '\x1b']).next()?;
+    let mut channels = rgb.split('/').map(|channel| {
+        let digits = channel.len() as u32;
+        let value = u32::from_str_radix(channel, 16).ok()?;
+        (1..=4)
+            .contains(&digits)
+            .then(|| (value * 255 / (16u32.pow(digits) - 1)) as u8)
+    });
+    Some((channels.next()??, channels.next()??, channels.next()??))
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Makes Ctrl-C set a flag checked by [interrupted] instead of killing motd, so slow output can
+/// be finished cleanly
+#[cfg(unix)]
+pub fn catch_interrupts() {
+    let handler = on_interrupt as extern "C" fn(libc::c_int);
+    unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+}
+
+#[cfg(not(unix))]
+pub fn catch_interrupts() {}
+
+/// Lets Ctrl-C kill motd again after [catch_interrupts], and does so at once if it was pressed
+/// in the meantime
+#[cfg(unix)]
+fn restore_interrupts() {
+    unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL) };
+    if interrupted() {
+        unsafe { libc::raise(libc::SIGINT) };
+    }
+}
+
+/// Whether Ctrl-C has been pressed since [catch_interrupts]
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_columns_win() {
+        let width = pick_width(Some(40), || panic!("the terminal isn't asked"), Some("100"));
+        assert_eq!(width, 40);
+    }
+
+    #[test]
+    fn terminal_width_comes_before_columns_env() {
+        assert_eq!(pick_width(None, || Some(120), Some("100")), 120);
+    }
+
+    #[test]
+    fn columns_env_is_used_without_a_terminal() {
+        assert_eq!(pick_width(None, || None, Some("100")), 100);
+    }
+
+    #[test]
+    fn width_defaults_to_80() {
+        assert_eq!(pick_width(None, || None, None), 80);
+        assert_eq!(pick_width(None, || None, Some("0")), 80);
+        assert_eq!(pick_width(None, || None, Some("wide")), 80);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn color_reply_is_parsed_before_the_da1_reply() {
+        let reply = "\x1b]11;rgb:ffff/8080/0000\x1b\\\x1b[?62;22c";
+        assert!(ends_with_da1_reply(reply.as_bytes()));
+        assert_eq!(parse_color_reply(reply), Some((255, 128, 0)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn da1_reply_alone_has_no_color() {
+        let reply = "\x1b[?1;2c";
+        assert!(ends_with_da1_reply(reply.as_bytes()));
+        assert_eq!(parse_color_reply(reply), None);
+    }
+}
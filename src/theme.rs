@@ -0,0 +1,59 @@
+//! Built-in color themes
+
+use termcolor::Color;
+
+/// A palette that text colors are picked from, in place of fully random hues
+pub struct Theme {
+    pub name: &'static str,
+    pub colors: &'static [Color],
+    /// Whether the text is bold by default
+    pub bold: bool,
+}
+
+pub const THEMES: [Theme; 3] = [
+    Theme {
+        name: "gruvbox",
+        colors: &[
+            Color::Rgb(0xfb, 0x49, 0x34),
+            Color::Rgb(0xb8, 0xbb, 0x26),
+            Color::Rgb(0xfa, 0xbd, 0x2f),
+            Color::Rgb(0x83, 0xa5, 0x98),
+            Color::Rgb(0xd3, 0x86, 0x9b),
+            Color::Rgb(0x8e, 0xc0, 0x7c),
+            Color::Rgb(0xfe, 0x80, 0x19),
+        ],
+        bold: false,
+    },
+    Theme {
+        name: "nord",
+        colors: &[
+            Color::Rgb(0x88, 0xc0, 0xd0),
+            Color::Rgb(0x81, 0xa1, 0xc1),
+            Color::Rgb(0xbf, 0x61, 0x6a),
+            Color::Rgb(0xd0, 0x87, 0x70),
+            Color::Rgb(0xeb, 0xcb, 0x8b),
+            Color::Rgb(0xa3, 0xbe, 0x8c),
+            Color::Rgb(0xb4, 0x8e, 0xad),
+        ],
+        bold: false,
+    },
+    Theme {
+        name: "mono",
+        colors: &[Color::Rgb(0xe0, 0xe0, 0xe0)],
+        bold: true,
+    },
+];
+
+/// Looks up a built-in theme by name
+pub fn find(name: &str) -> Result<&'static Theme, String> {
+    THEMES
+        .iter()
+        .find(|theme| theme.name == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = THEMES.iter().map(|theme| theme.name).collect();
+            format!(
+                "unknown theme '{name}' (expected one of {})",
+                names.join(", ")
+            )
+        })
+}
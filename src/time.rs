@@ -0,0 +1,239 @@
+//! Local date and time, formatted through the C library
+
+#[cfg(unix)]
+use std::ffi::{c_char, CString};
+
+/// Formats the current local time with a strftime-style `format` string, or returns `None` if
+/// the format can't be used or the local time can't be determined
+#[cfg(unix)]
+pub fn strftime(format: &str) -> Option<String> {
+    if format.is_empty() {
+        return Some(String::new());
+    }
+    let format = CString::new(format).ok()?;
+
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return None;
+        }
+    }
+
+    // strftime returns 0 both for an empty result and a buffer that's too small, so retry a
+    // few times with more room before giving up
+    let mut buf = vec![0u8; 128];
+    while buf.len() <= 4096 {
+        let len = unsafe {
+            libc::strftime(
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len(),
+                format.as_ptr(),
+                &tm,
+            )
+        };
+        if len > 0 {
+            buf.truncate(len);
+            return String::from_utf8(buf).ok();
+        }
+        buf.resize(buf.len() * 2, 0);
+    }
+    None
+}
+
+/// Formats the current local time like the unix version, but only knows the common fields:
+/// `%Y %y %m %d %e %j %H %I %M %S %p %a %A %b %B %u %w %F %T %R %%`
+#[cfg(windows)]
+pub fn strftime(format: &str) -> Option<String> {
+    use windows_sys::Win32::Foundation::SYSTEMTIME;
+    use windows_sys::Win32::System::SystemInformation::GetLocalTime;
+
+    let mut now: SYSTEMTIME = unsafe { std::mem::zeroed() };
+    unsafe { GetLocalTime(&mut now) };
+    format_time(
+        format,
+        &LocalTime {
+            year: now.wYear as i64,
+            month: now.wMonth as u32,
+            day: now.wDay as u32,
+            hour: now.wHour as u32,
+            minute: now.wMinute as u32,
+            second: now.wSecond as u32,
+        },
+    )
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn strftime(_format: &str) -> Option<String> {
+    None
+}
+
+/// A local date and time, for formatting without the C library
+#[cfg(any(windows, test))]
+struct LocalTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+}
+
+#[cfg(any(windows, test))]
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+#[cfg(any(windows, test))]
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Formats `time` with the strftime fields listed on the Windows [strftime], returning `None`
+/// for any other field
+#[cfg(any(windows, test))]
+fn format_time(format: &str, time: &LocalTime) -> Option<String> {
+    let days = days_from_civil(time.year, time.month, time.day);
+    // 1970-01-01 was a Thursday, and the week starts on Monday
+    let weekday = (days + 3).rem_euclid(7) as usize;
+    let weekday_name = WEEKDAYS[weekday];
+    let month_name = MONTHS[time.month.checked_sub(1)? as usize % 12];
+    let hour12 = (time.hour + 11) % 12 + 1;
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let field = match chars.next()? {
+            'Y' => time.year.to_string(),
+            'y' => format!("{:02}", time.year.rem_euclid(100)),
+            'm' => format!("{:02}", time.month),
+            'd' => format!("{:02}", time.day),
+            'e' => format!("{:2}", time.day),
+            'j' => format!("{:03}", days - days_from_civil(time.year, 1, 1) + 1),
+            'H' => format!("{:02}", time.hour),
+            'I' => format!("{hour12:02}"),
+            'M' => format!("{:02}", time.minute),
+            'S' => format!("{:02}", time.second),
+            'p' => if time.hour < 12 { "AM" } else { "PM" }.to_string(),
+            'a' => weekday_name[..3].to_string(),
+            'A' => weekday_name.to_string(),
+            'b' => month_name[..3].to_string(),
+            'B' => month_name.to_string(),
+            'u' => (weekday + 1).to_string(),
+            'w' => ((weekday + 1) % 7).to_string(),
+            'F' => format!("{}-{:02}-{:02}", time.year, time.month, time.day),
+            'T' => format!("{:02}:{:02}:{:02}", time.hour, time.minute, time.second),
+            'R' => format!("{:02}:{:02}", time.hour, time.minute),
+            '%' => "%".to_string(),
+            _ => return None,
+        };
+        out.push_str(&field);
+    }
+    Some(out)
+}
+
+/// The number of days between the Unix epoch and today's local date
+pub fn today() -> Option<i64> {
+    strftime("%Y-%m-%d").and_then(|date| parse_date(&date))
+}
+
+/// Parses a `YYYY-MM-DD` date into a number of days since the Unix epoch
+pub fn parse_date(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar, as in Howard Hinnant's
+/// `days_from_civil`
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_fields_are_formatted_without_the_c_library() {
+        let time = LocalTime {
+            year: 2024,
+            month: 3,
+            day: 1,
+            hour: 15,
+            minute: 4,
+            second: 5,
+        };
+        assert_eq!(
+            format_time("%A, %B %e %Y", &time).as_deref(),
+            Some("Friday, March  1 2024")
+        );
+        assert_eq!(
+            format_time("%a %b %d %y %j", &time).as_deref(),
+            Some("Fri Mar 01 24 061")
+        );
+        assert_eq!(
+            format_time("%F %T %I%p %u %w %%", &time).as_deref(),
+            Some("2024-03-01 15:04:05 03PM 5 5 %")
+        );
+        assert_eq!(format_time("%Z", &time), None);
+    }
+
+    #[test]
+    fn weekdays_start_on_monday() {
+        let weekday = |day| {
+            let time = LocalTime {
+                year: 2024,
+                month: 3,
+                day,
+                hour: 0,
+                minute: 0,
+                second: 0,
+            };
+            format_time("%u %w %a", &time).unwrap()
+        };
+        assert_eq!(weekday(3), "7 0 Sun");
+        assert_eq!(weekday(4), "1 1 Mon");
+    }
+}
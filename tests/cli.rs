@@ -0,0 +1,123 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// A command running motd on the message file `fixture` from `tests/fixtures`
+fn command(fixture: &str) -> Command {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(fixture);
+    let mut command = Command::new(env!("CARGO_BIN_EXE_motd"));
+    command
+        .env("MOTD_FILE", path)
+        .env_remove("MOTD_WEIGHTING")
+        .env_remove("MOTD_LOG");
+    command
+}
+
+/// Runs motd on the message file `fixture` with `args` and its output piped
+fn motd(fixture: &str, args: &[&str]) -> Output {
+    command(fixture)
+        .args(args)
+        .output()
+        .expect("motd should run")
+}
+
+#[test]
+fn crlf_line_endings_are_stripped() {
+    let output = motd("crlf.conf", &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello from Windows\n"
+    );
+}
+
+#[test]
+fn invalid_utf8_is_replaced() {
+    let output = motd("latin1.conf", &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "caf\u{fffd} au lait\n"
+    );
+}
+
+#[test]
+fn weighting_hashes_invalid_utf8() {
+    let history = std::env::temp_dir().join(format!("motd-history-{}", std::process::id()));
+    let output = command("latin1.conf")
+        .env("MOTD_WEIGHTING", "inverse")
+        .env("MOTD_HISTORY_FILE", &history)
+        .output()
+        .expect("motd should run");
+    let saved = std::fs::read_to_string(&history);
+    let _ = std::fs::remove_file(&history);
+    assert!(output.status.success());
+    assert_eq!(saved.expect("history should be saved").lines().count(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn generate_keeps_the_replaced_file_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("motd-generate-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("motd");
+    std::fs::write(&target, "old\n-- generated by motd --\n").unwrap();
+    std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o600)).unwrap();
+
+    let output = motd("hello.conf", &["--generate", target.to_str().unwrap()]);
+    let contents = std::fs::read_to_string(&target).unwrap();
+    let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+    let leftovers = std::fs::read_dir(&dir).unwrap().count();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(output.status.success());
+    assert_eq!(contents, "Hello, world\n-- generated by motd --\n");
+    assert_eq!(mode & 0o777, 0o600);
+    assert_eq!(leftovers, 1);
+}
+
+#[test]
+fn no_trim_keeps_indentation() {
+    let output = motd("art.conf", &["--no-trim"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "    (\\_/)   (o.o)\n"
+    );
+    // Wrapped pieces keep the indentation of the line
+    let output = motd(
+        "art.conf",
+        &["--no-trim", "--columns", "12", "--max-lines", "5"],
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "    (\\_/)\n    (o.o)\n"
+    );
+    let output = motd("art.conf", &[]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "(\\_/)   (o.o)\n");
+}
+
+#[test]
+fn piped_output_is_plain_text() {
+    let output = motd("tokens.conf", &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hi site (https://example.com)! {x}\n"
+    );
+}
+
+#[test]
+fn closed_pipe_is_not_an_error() {
+    // The reading end is closed before motd starts, so its write always fails with EPIPE
+    let (reader, writer) = std::io::pipe().expect("a pipe should be created");
+    drop(reader);
+    let output = command("hello.conf")
+        .stdout(writer)
+        .output()
+        .expect("motd should run");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}